@@ -1,10 +1,12 @@
 import React, { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import ChatBox from "./components/ChatBox";
-import FileUploader from "./components/FileUploader";
+import FileUploader, { UploadedDocument } from "./components/FileUploader";
 import ModelVerifier from "./components/ModelVerifier";
 import ModelManager from "./components/ModelManager";
+import GatewayPanel, { describeGateway, GatewayStatus } from "./components/GatewayPanel";
 import axios from "axios";
+import { describeError } from "./problem";
 
 type Message = {
   role: "user" | "assistant";
@@ -17,11 +19,11 @@ export default function App() {
   // ---- Chat state ----
   const [messages, setMessages] = useState<Message[]>([]);
   const [loading, setLoading] = useState(false);
+  const [documents, setDocuments] = useState<UploadedDocument[]>([]);
 
   // ---- Gateway + Version state ----
   const [version, setVersion] = useState<string>("loading…");
-  const [gatewayStatus, setGatewayStatus] = useState<string>("Gateway idle");
-  const [error, setError] = useState<string | null>(null);
+  const [gatewayStatus, setGatewayStatus] = useState<GatewayStatus | null>(null);
 
   useEffect(() => {
     invoke<string>("app_version")
@@ -29,36 +31,28 @@ export default function App() {
       .catch(() => setVersion("unknown"));
   }, []);
 
-  async function startLocalGateway() {
-    try {
-      const response = await invoke<string>("start_mcp_gateway");
-      setGatewayStatus(response);
-      setError(null);
-    } catch (err) {
-      console.error(err);
-      setError(err instanceof Error ? err.message : String(err));
-    }
-  }
-
   // ---- Chat logic ----
   async function handleSend(query: string) {
     setMessages(prev => [...prev, { role: "user", text: query }]);
     setLoading(true);
 
     try {
-      const { data: reasonReq } = await axios.post(`${API_URL}/query`, { text: query });
-      const { data: reasonRes } = await axios.post(`${API_URL}/reason`, reasonReq);
+      const { data: reasonReq } = await axios.post(`${API_URL}/v1/query`, {
+        text: query,
+        documents: documents.map(d => d.id),
+      });
+      const { data: reasonRes } = await axios.post(`${API_URL}/v1/reason`, reasonReq);
 
       setMessages(prev => [
         ...prev,
         { role: "assistant", text: reasonRes.summary || "No response." },
       ]);
 
-      await axios.post(`${API_URL}/store`, reasonRes);
-    } catch (e: any) {
+      await axios.post(`${API_URL}/v1/store`, reasonRes);
+    } catch (e) {
       setMessages(prev => [
         ...prev,
-        { role: "assistant", text: `Error: ${e.message}` },
+        { role: "assistant", text: `Error: ${describeError(e)}` },
       ]);
     } finally {
       setLoading(false);
@@ -71,21 +65,12 @@ export default function App() {
       <header className="p-4 border-b border-gray-700 flex justify-between items-center">
         <div className="text-xl font-semibold">⚖️ Counsel AI Desktop</div>
         <div className="text-xs text-gray-400">
-          v{version} — {gatewayStatus}
+          v{version} — {describeGateway(gatewayStatus)}
         </div>
       </header>
 
       {/* ---- Gateway Controls ---- */}
-      <div className="p-2 bg-gray-800 border-b border-gray-700 flex items-center justify-between">
-        <button
-          type="button"
-          onClick={startLocalGateway}
-          className="bg-blue-600 hover:bg-blue-700 text-white px-3 py-1 rounded text-sm"
-        >
-          Start Local MCP Gateway
-        </button>
-        {error && <span className="text-red-400 text-xs">{error}</span>}
-      </div>
+      <GatewayPanel onStatus={setGatewayStatus} />
 
       {/* ---- Chat Window ---- */}
       <main className="flex-1 overflow-y-auto">
@@ -96,7 +81,7 @@ export default function App() {
       <footer className="border-t border-gray-700 p-4 flex flex-col gap-4">
         <ModelVerifier />
         <ModelManager />
-        <FileUploader />
+        <FileUploader apiUrl={API_URL} onUploaded={doc => setDocuments(prev => [...prev, doc])} />
         <form
           onSubmit={e => {
             e.preventDefault();
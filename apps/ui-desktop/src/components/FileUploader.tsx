@@ -1,12 +1,40 @@
 import React, { useState } from "react";
+import axios from "axios";
+import { describeError } from "../problem";
 
-export default function FileUploader() {
+/** A document ingested by the gateway's `/v1/documents`. */
+export type UploadedDocument = {
+  id: string;
+  filename: string;
+  characters: number;
+};
+
+type Props = {
+  apiUrl: string;
+  onUploaded: (doc: UploadedDocument) => void;
+};
+
+export default function FileUploader({ apiUrl, onUploaded }: Props) {
   const [fileNames, setFileNames] = useState<string[]>([]);
+  const [error, setError] = useState<string | null>(null);
+  const [uploading, setUploading] = useState(false);
 
-  const handleFiles = (files: FileList | null) => {
+  const handleFiles = async (files: FileList | null) => {
     if (!files) return;
-    const names = Array.from(files).map(f => f.name);
-    setFileNames(prev => [...prev, ...names]);
+    setError(null);
+    setUploading(true);
+    for (const file of Array.from(files)) {
+      const form = new FormData();
+      form.append("file", file);
+      try {
+        const { data } = await axios.post<UploadedDocument>(`${apiUrl}/v1/documents`, form);
+        setFileNames(prev => [...prev, data.filename]);
+        onUploaded(data);
+      } catch (e) {
+        setError(`${file.name}: ${describeError(e)}`);
+      }
+    }
+    setUploading(false);
   };
 
   return (
@@ -15,14 +43,18 @@ export default function FileUploader() {
       <input
         type="file"
         multiple
+        accept=".pdf,.docx,.txt"
+        disabled={uploading}
         onChange={e => handleFiles(e.target.files)}
         className="text-sm text-gray-300"
       />
+      {uploading && <div className="text-xs text-gray-400">Uploading…</div>}
       {fileNames.length > 0 && (
         <div className="text-xs text-gray-400">
           Attached: {fileNames.join(", ")}
         </div>
       )}
+      {error && <div className="text-xs text-red-400">{error}</div>}
     </div>
   );
 }
@@ -0,0 +1,159 @@
+import React, { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
+
+/** Payload of the `gateway-status` event, mirrored from the supervisor. */
+export type GatewayStatus = {
+  state:
+    | "stopped"
+    | "starting"
+    | "healthy"
+    | "degraded"
+    | "unhealthy"
+    | "crashed"
+    | "backing_off"
+    | "failed";
+  pid: number | null;
+  port: number;
+  restarts: number;
+  http_status?: number;
+  failed_probes?: number;
+  exit_code?: number | null;
+  attempt?: number;
+  retry_in_ms?: number;
+  message?: string;
+};
+
+type LogLine = {
+  timestamp_ms: number;
+  stream: "stdout" | "stderr";
+  line: string;
+};
+
+const LOG_LIMIT = 1000;
+
+export function describeGateway(status: GatewayStatus | null): string {
+  if (!status) return "Gateway idle";
+  switch (status.state) {
+    case "stopped":
+      return "Gateway stopped";
+    case "starting":
+      return "Gateway starting…";
+    case "healthy":
+      return `Gateway healthy on :${status.port}`;
+    case "degraded":
+      return `Gateway running, not ready (HTTP ${status.http_status})`;
+    case "unhealthy":
+      return `Gateway not responding (${status.failed_probes} failed checks)`;
+    case "crashed":
+      return `Gateway exited (code ${status.exit_code ?? "?"})`;
+    case "backing_off":
+      return `Gateway restarting in ${Math.round((status.retry_in_ms ?? 0) / 1000)}s (attempt ${status.attempt})`;
+    case "failed":
+      return `Gateway failed: ${status.message}`;
+  }
+}
+
+const STATE_CLASSES: Record<GatewayStatus["state"], string> = {
+  stopped: "text-gray-400",
+  starting: "text-yellow-400",
+  healthy: "text-green-400",
+  degraded: "text-yellow-400",
+  unhealthy: "text-orange-400",
+  crashed: "text-red-400",
+  backing_off: "text-orange-400",
+  failed: "text-red-400",
+};
+
+export default function GatewayPanel({ onStatus }: { onStatus?: (status: GatewayStatus) => void }) {
+  const [status, setStatus] = useState<GatewayStatus | null>(null);
+  const [logs, setLogs] = useState<LogLine[]>([]);
+  const [showLogs, setShowLogs] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  function applyStatus(next: GatewayStatus) {
+    setStatus(next);
+    onStatus?.(next);
+  }
+
+  useEffect(() => {
+    invoke<GatewayStatus>("gateway_status").then(applyStatus).catch(() => {});
+    invoke<LogLine[]>("gateway_logs").then(setLogs).catch(() => {});
+    const unlistenStatus = listen<GatewayStatus>("gateway-status", e => applyStatus(e.payload));
+    const unlistenLog = listen<LogLine>("gateway-log", e =>
+      setLogs(prev => [...prev.slice(-(LOG_LIMIT - 1)), e.payload])
+    );
+    return () => {
+      unlistenStatus.then(f => f());
+      unlistenLog.then(f => f());
+    };
+  }, []);
+
+  async function control(command: "start_mcp_gateway" | "stop_mcp_gateway" | "restart_mcp_gateway") {
+    try {
+      applyStatus(await invoke<GatewayStatus>(command));
+      setError(null);
+    } catch (err) {
+      console.error(err);
+      setError(err instanceof Error ? err.message : String(err));
+    }
+  }
+
+  const running = status !== null && !["stopped", "failed"].includes(status.state);
+
+  return (
+    <div className="bg-gray-800 border-b border-gray-700">
+      <div className="p-2 flex items-center justify-between gap-2">
+        <div className="flex gap-2">
+          {running ? (
+            <>
+              <button
+                type="button"
+                onClick={() => control("restart_mcp_gateway")}
+                className="bg-blue-600 hover:bg-blue-700 text-white px-3 py-1 rounded text-sm"
+              >
+                Restart Gateway
+              </button>
+              <button
+                type="button"
+                onClick={() => control("stop_mcp_gateway")}
+                className="bg-gray-600 hover:bg-gray-700 text-white px-3 py-1 rounded text-sm"
+              >
+                Stop
+              </button>
+            </>
+          ) : (
+            <button
+              type="button"
+              onClick={() => control("start_mcp_gateway")}
+              className="bg-blue-600 hover:bg-blue-700 text-white px-3 py-1 rounded text-sm"
+            >
+              Start Local MCP Gateway
+            </button>
+          )}
+          <button
+            type="button"
+            onClick={() => setShowLogs(v => !v)}
+            className="text-gray-300 hover:text-white px-2 py-1 text-sm"
+          >
+            {showLogs ? "Hide logs" : "Show logs"}
+          </button>
+        </div>
+        <span className={`text-xs ${status ? STATE_CLASSES[status.state] : "text-gray-400"}`}>
+          {describeGateway(status)}
+          {status && status.restarts > 0 && ` — ${status.restarts} restart(s)`}
+        </span>
+        {error && <span className="text-red-400 text-xs">{error}</span>}
+      </div>
+      {showLogs && (
+        <pre className="max-h-48 overflow-y-auto bg-gray-900 text-xs p-2 whitespace-pre-wrap">
+          {logs.map((l, i) => (
+            <div key={i} className={l.stream === "stderr" ? "text-gray-300" : "text-gray-400"}>
+              {new Date(l.timestamp_ms).toLocaleTimeString()} {l.line}
+            </div>
+          ))}
+        </pre>
+      )}
+    </div>
+  );
+}
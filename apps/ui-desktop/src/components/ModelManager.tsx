@@ -1,16 +1,42 @@
 import React, { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
+
+type ModelStatus = "not_installed" | "unverified" | "verified" | "corrupt" | "untrusted";
 
 type Model = {
+  id: string | null;
   name: string;
   file: string;
-  installed: boolean;
   path: string;
+  size_bytes: number;
+  role: "instruct" | "embedding" | null;
+  status: ModelStatus;
+  active: boolean;
+};
+
+const STATUS_LABELS: Record<ModelStatus, { text: string; className: string }> = {
+  not_installed: { text: "Not installed", className: "text-gray-400" },
+  unverified: { text: "Installed — not verified", className: "text-yellow-400" },
+  verified: { text: "Verified ✅", className: "text-green-400" },
+  corrupt: { text: "Corrupt — hash mismatch", className: "text-red-400" },
+  untrusted: { text: "Not in trusted registry ⚠️", className: "text-orange-400" },
 };
 
+function formatSize(bytes: number): string {
+  return `${(bytes / 1024 ** 3).toFixed(1)} GB`;
+}
+
+/** JSON progress line emitted by `model-manager`, or by the desktop's own repair download. */
+type InstallEvent =
+  | { event: "stage"; id: string; stage: "downloading" | "verifying" }
+  | { event: "progress"; id: string; downloaded: number; total: number | null }
+  | { event: "verified"; id: string; sha256: string }
+  | { event: "installed"; id: string; path: string }
+  | { event: "error"; message: string };
+
 export default function ModelManager() {
   const [models, setModels] = useState<Model[]>([]);
-  const [activePath, setActivePath] = useState<string>("");
   const [status, setStatus] = useState<string>("");
 
   async function loadModels() {
@@ -26,10 +52,10 @@ export default function ModelManager() {
     }
   }
 
-  async function handleInstall(name: string) {
-    setStatus(`Installing ${name} ...`);
+  async function handleInstall(id: string) {
+    setStatus(`Installing ${id} ...`);
     try {
-      const res = await invoke<string>("install_model", { modelName: name });
+      const res = await invoke<string>("install_model", { modelId: id });
       setStatus(res);
       await loadModels();
     } catch (error: any) {
@@ -41,11 +67,26 @@ export default function ModelManager() {
     }
   }
 
+  async function handleVerify(id: string) {
+    setStatus(`Verifying ${id} ...`);
+    try {
+      const result = await invoke<ModelStatus>("verify_model", { modelId: id });
+      setStatus(`${id}: ${STATUS_LABELS[result].text}`);
+      await loadModels();
+    } catch (error: any) {
+      const message =
+        typeof error === "string"
+          ? error
+          : error?.toString?.() ?? "Unknown error";
+      setStatus(`Error: ${message}`);
+    }
+  }
+
   async function handleActivate(path: string) {
     try {
       const res = await invoke<string>("set_active_model", { modelPath: path });
       setStatus(res);
-      setActivePath(path);
+      await loadModels();
     } catch (error: any) {
       const message =
         typeof error === "string"
@@ -57,6 +98,19 @@ export default function ModelManager() {
 
   useEffect(() => {
     loadModels();
+    const unlisten = listen<InstallEvent>("model-install-progress", ({ payload }) => {
+      if (payload.event === "stage") {
+        setStatus(`${payload.id}: ${payload.stage} ...`);
+      } else if (payload.event === "progress") {
+        const done = payload.total ? ` / ${formatSize(payload.total)}` : "";
+        setStatus(`${payload.id}: ${formatSize(payload.downloaded)}${done}`);
+      } else if (payload.event === "error") {
+        setStatus(`Error: ${payload.message}`);
+      }
+    });
+    return () => {
+      unlisten.then(stop => stop());
+    };
   }, []);
 
   return (
@@ -65,40 +119,49 @@ export default function ModelManager() {
       <div className="space-y-2">
         {models.map((m) => (
           <div
-            key={m.name}
+            key={m.id ?? m.path}
             className="flex items-center justify-between bg-gray-900 p-2 rounded-md"
           >
             <div>
               <div className="font-medium text-gray-100">{m.name}</div>
-              <div className="text-xs text-gray-400">{m.file}</div>
-              {m.installed ? (
-                <div className="text-xs text-green-400">Installed ✅</div>
-              ) : (
-                <div className="text-xs text-red-400">Not installed</div>
-              )}
-            </div>
-            <div className="flex gap-2">
-              {!m.installed && (
-                <button
-                  onClick={() => handleInstall(m.name)}
-                  className="bg-blue-600 hover:bg-blue-700 px-3 py-1 rounded text-sm"
-                >
-                  Install
-                </button>
-              )}
-              {m.installed && (
-                <button
-                  onClick={() => handleActivate(m.path)}
-                  className={`px-3 py-1 rounded text-sm ${
-                    activePath === m.path
-                      ? "bg-green-600"
-                      : "bg-gray-700 hover:bg-green-700"
-                  }`}
-                >
-                  {activePath === m.path ? "Active" : "Activate"}
-                </button>
-              )}
+              <div className="text-xs text-gray-400">
+                {m.file} · {formatSize(m.size_bytes)}
+                {m.role && ` · ${m.role}`}
+              </div>
+              <div className={`text-xs ${STATUS_LABELS[m.status].className}`}>
+                {STATUS_LABELS[m.status].text}
+              </div>
             </div>
+            {m.id && (
+              <div className="flex gap-2">
+                {m.status === "not_installed" && (
+                  <button
+                    onClick={() => handleInstall(m.id!)}
+                    className="bg-blue-600 hover:bg-blue-700 px-3 py-1 rounded text-sm"
+                  >
+                    Install
+                  </button>
+                )}
+                {(m.status === "unverified" || m.status === "corrupt") && (
+                  <button
+                    onClick={() => handleVerify(m.id!)}
+                    className="bg-gray-700 hover:bg-yellow-700 px-3 py-1 rounded text-sm"
+                  >
+                    Verify
+                  </button>
+                )}
+                {m.status === "verified" && (
+                  <button
+                    onClick={() => handleActivate(m.path)}
+                    className={`px-3 py-1 rounded text-sm ${
+                      m.active ? "bg-green-600" : "bg-gray-700 hover:bg-green-700"
+                    }`}
+                  >
+                    {m.active ? "Active" : "Activate"}
+                  </button>
+                )}
+              </div>
+            )}
           </div>
         ))}
       </div>
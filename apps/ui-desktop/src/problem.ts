@@ -0,0 +1,60 @@
+import axios from "axios";
+
+/** Stable error codes sent by the gateway in `application/problem+json` bodies. */
+export type ProblemCode =
+  | "validation_failed"
+  | "policy_violation"
+  | "payload_too_large"
+  | "unsupported_media_type"
+  | "not_found"
+  | "unauthorized"
+  | "rate_limited"
+  | "upstream_failed"
+  | "model_unavailable"
+  | "storage_failed";
+
+/** RFC 7807 problem details as returned by the gateway. */
+export type Problem = {
+  type: string;
+  title: string;
+  status: number;
+  detail: string;
+  code: ProblemCode;
+  errors?: unknown;
+};
+
+export function asProblem(err: unknown): Problem | null {
+  if (!axios.isAxiosError(err)) return null;
+  const data = err.response?.data;
+  return data && typeof data === "object" && "code" in data ? (data as Problem) : null;
+}
+
+/** A user-facing message for a failed gateway call; branches on `code`, never on text. */
+export function describeError(err: unknown): string {
+  const problem = asProblem(err);
+  if (!problem) return err instanceof Error ? err.message : String(err);
+  switch (problem.code) {
+    case "validation_failed":
+      return `Please check your input: ${problem.detail}`;
+    case "policy_violation":
+      return "This request was blocked by the content policy.";
+    case "payload_too_large":
+      return "The request is too large.";
+    case "unsupported_media_type":
+      return `This file cannot be read: ${problem.detail}`;
+    case "unauthorized":
+      return "The gateway rejected the API key. Check the gateway configuration.";
+    case "rate_limited": {
+      const retry = axios.isAxiosError(err) ? err.response?.headers["retry-after"] : undefined;
+      return `Too many requests — try again${retry ? ` in ${retry}s` : " shortly"}.`;
+    }
+    case "upstream_failed":
+      return "The online reasoner is unavailable. Try again, or use the local model.";
+    case "model_unavailable":
+      return "The local model is unavailable. Install or verify a model below.";
+    case "storage_failed":
+      return "The answer could not be saved to the audit log.";
+    default:
+      return problem.detail || problem.title;
+  }
+}
@@ -0,0 +1,362 @@
+//! Supervisor for the local `mcp-gateway` process.
+//! Owns the child handle, polls its `/livez` and `/readyz` probes, keeps recent output in a
+//! ring buffer, restarts it with exponential backoff when it dies and pushes every
+//! state change to the UI as a `gateway-status` event.
+
+use std::{
+    collections::VecDeque,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::{async_runtime::JoinHandle, AppHandle, Emitter};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::{Child, Command},
+    sync::watch,
+};
+
+pub const GATEWAY_PORT: u16 = 5142;
+const LOG_CAPACITY: usize = 1000;
+const HEALTH_INTERVAL: Duration = Duration::from_secs(2);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a fresh process may take to open its port before probes count as failures.
+const STARTUP_GRACE: Duration = Duration::from_secs(30);
+/// Consecutive failed probes before the gateway is reported unhealthy.
+const UNHEALTHY_AFTER: u32 = 3;
+/// Consecutive failed probes before a hung gateway is killed and restarted.
+const RESTART_AFTER: u32 = 15;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A process that stayed up this long resets the backoff sequence.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum GatewayState {
+    Stopped,
+    Starting,
+    /// Alive and `/readyz` answered 2xx.
+    Healthy,
+    /// Alive, but `/readyz` reports a readiness criterion failing.
+    Degraded { http_status: u16 },
+    /// The process is alive but its port stopped answering.
+    Unhealthy { failed_probes: u32 },
+    /// The process exited on its own.
+    Crashed { exit_code: Option<i32> },
+    BackingOff { attempt: u32, retry_in_ms: u64 },
+    /// The gateway could not be launched; supervision stopped.
+    Failed { message: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayStatus {
+    #[serde(flatten)]
+    pub state: GatewayState,
+    pub pid: Option<u32>,
+    pub port: u16,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp_ms: u64,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+/// State shared between the Tauri commands and the supervision task.
+#[derive(Clone)]
+struct Shared {
+    app: AppHandle,
+    status: Arc<Mutex<GatewayStatus>>,
+    logs: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl Shared {
+    fn update(&self, f: impl FnOnce(&mut GatewayStatus)) {
+        let snapshot = {
+            let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+            let before = status.clone();
+            f(&mut status);
+            if status.state == before.state && status.pid == before.pid {
+                return;
+            }
+            status.clone()
+        };
+        self.app.emit("gateway-status", &snapshot).ok();
+    }
+
+    fn set_state(&self, state: GatewayState) {
+        self.update(|s| s.state = state);
+    }
+
+    fn push_log(&self, stream: LogStream, line: String) {
+        let entry = LogLine {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            stream,
+            line,
+        };
+        {
+            let mut logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+            if logs.len() == LOG_CAPACITY {
+                logs.pop_front();
+            }
+            logs.push_back(entry.clone());
+        }
+        self.app.emit("gateway-log", &entry).ok();
+    }
+}
+
+struct Supervision {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// Managed Tauri state; see the module docs.
+pub struct GatewaySupervisor {
+    status: Arc<Mutex<GatewayStatus>>,
+    logs: Arc<Mutex<VecDeque<LogLine>>>,
+    supervision: tokio::sync::Mutex<Option<Supervision>>,
+}
+
+impl Default for GatewaySupervisor {
+    fn default() -> Self {
+        Self {
+            status: Arc::new(Mutex::new(GatewayStatus {
+                state: GatewayState::Stopped,
+                pid: None,
+                port: GATEWAY_PORT,
+                restarts: 0,
+            })),
+            logs: Arc::new(Mutex::new(VecDeque::with_capacity(LOG_CAPACITY))),
+            supervision: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl GatewaySupervisor {
+    pub fn status(&self) -> GatewayStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn logs(&self) -> Vec<LogLine> {
+        self.logs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Start supervising the gateway with `working_dir` as its current directory, so it
+    /// picks up the app's `.env`. Does nothing if supervision is already running.
+    pub async fn start(&self, app: AppHandle, working_dir: PathBuf) -> GatewayStatus {
+        let mut supervision = self.supervision.lock().await;
+        if supervision.as_ref().is_some_and(|s| !s.task.inner().is_finished()) {
+            return self.status();
+        }
+
+        let shared = Shared {
+            app,
+            status: Arc::clone(&self.status),
+            logs: Arc::clone(&self.logs),
+        };
+        shared.update(|s| s.restarts = 0);
+        let (stop, stop_rx) = watch::channel(false);
+        let task = tauri::async_runtime::spawn(supervise(shared, working_dir, stop_rx));
+        *supervision = Some(Supervision { stop, task });
+        self.status()
+    }
+
+    /// Stop supervising and terminate the gateway if it is running.
+    pub async fn stop(&self) -> GatewayStatus {
+        if let Some(Supervision { stop, task }) = self.supervision.lock().await.take() {
+            stop.send(true).ok();
+            task.await.ok();
+        }
+        self.status()
+    }
+}
+
+fn gateway_command(working_dir: &Path) -> Command {
+    let mut command = if cfg!(target_os = "windows") {
+        Command::new("mcp-gateway.exe")
+    } else {
+        Command::new("mcp-gateway")
+    };
+    // Read on every spawn so a restart picks up a newly activated model.
+    if let Some(model_path) = crate::active_model_path(&working_dir.join(".env")) {
+        command.env("LOCAL_MODEL_PATH", model_path);
+    }
+    command
+        .current_dir(working_dir)
+        .env("BIND_ADDR", SocketAddr::from((Ipv4Addr::LOCALHOST, GATEWAY_PORT)).to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    command
+}
+
+fn capture_output(shared: &Shared, child: &mut Child) {
+    fn pump(shared: Shared, stream: LogStream, reader: impl AsyncRead + Unpin + Send + 'static) {
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                shared.push_log(stream, line);
+            }
+        });
+    }
+
+    if let Some(stdout) = child.stdout.take() {
+        pump(shared.clone(), LogStream::Stdout, stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        pump(shared.clone(), LogStream::Stderr, stderr);
+    }
+}
+
+enum Probe {
+    Healthy,
+    Degraded(u16),
+    Unreachable,
+}
+
+/// Liveness decides restarts; readiness only affects the reported state.
+async fn probe(client: &reqwest::Client) -> Probe {
+    let url = |path: &str| format!("http://127.0.0.1:{GATEWAY_PORT}{path}");
+    match client.get(url("/livez")).send().await {
+        Ok(response) if response.status().is_success() => {}
+        _ => return Probe::Unreachable,
+    }
+    match client.get(url("/readyz")).send().await {
+        Ok(response) if response.status().is_success() => Probe::Healthy,
+        Ok(response) => Probe::Degraded(response.status().as_u16()),
+        Err(_) => Probe::Unreachable,
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+async fn supervise(shared: Shared, working_dir: PathBuf, mut stop: watch::Receiver<bool>) {
+    let client = match reqwest::Client::builder().timeout(HEALTH_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            shared.set_state(GatewayState::Failed { message: e.to_string() });
+            return;
+        }
+    };
+    let mut attempt = 0u32;
+
+    loop {
+        if TcpListener::bind((Ipv4Addr::LOCALHOST, GATEWAY_PORT)).is_err() {
+            shared.set_state(GatewayState::Failed {
+                message: format!("port {GATEWAY_PORT} is already in use by another process"),
+            });
+            return;
+        }
+
+        shared.update(|s| {
+            s.state = GatewayState::Starting;
+            s.pid = None;
+        });
+        let mut child = match gateway_command(&working_dir).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                shared.set_state(GatewayState::Failed {
+                    message: format!("failed to start mcp-gateway: {e}"),
+                });
+                return;
+            }
+        };
+        shared.update(|s| s.pid = child.id());
+        capture_output(&shared, &mut child);
+
+        let started = Instant::now();
+        let mut ticker = tokio::time::interval(HEALTH_INTERVAL);
+        let mut failed_probes = 0u32;
+        let mut answered = false;
+
+        let exit_code = loop {
+            tokio::select! {
+                _ = stop.changed() => {
+                    child.kill().await.ok();
+                    shared.update(|s| {
+                        s.state = GatewayState::Stopped;
+                        s.pid = None;
+                    });
+                    return;
+                }
+                exit = child.wait() => break exit.ok().and_then(|status| status.code()),
+                _ = ticker.tick() => match probe(&client).await {
+                    Probe::Healthy => {
+                        answered = true;
+                        failed_probes = 0;
+                        shared.set_state(GatewayState::Healthy);
+                    }
+                    Probe::Degraded(http_status) => {
+                        answered = true;
+                        failed_probes = 0;
+                        shared.set_state(GatewayState::Degraded { http_status });
+                    }
+                    Probe::Unreachable if !answered && started.elapsed() < STARTUP_GRACE => {}
+                    Probe::Unreachable => {
+                        failed_probes += 1;
+                        if failed_probes >= RESTART_AFTER {
+                            shared.push_log(LogStream::Stderr, format!(
+                                "supervisor: gateway unresponsive for {failed_probes} probes, restarting"
+                            ));
+                            child.kill().await.ok();
+                        } else if failed_probes >= UNHEALTHY_AFTER {
+                            shared.set_state(GatewayState::Unhealthy { failed_probes });
+                        }
+                    }
+                },
+            }
+        };
+
+        if started.elapsed() >= STABLE_AFTER {
+            attempt = 0;
+        }
+        attempt += 1;
+        let delay = backoff(attempt);
+        shared.update(|s| {
+            s.state = GatewayState::Crashed { exit_code };
+            s.pid = None;
+        });
+        shared.update(|s| {
+            s.state = GatewayState::BackingOff {
+                attempt,
+                retry_in_ms: delay.as_millis() as u64,
+            };
+            s.restarts += 1;
+        });
+
+        tokio::select! {
+            _ = stop.changed() => {
+                shared.set_state(GatewayState::Stopped);
+                return;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
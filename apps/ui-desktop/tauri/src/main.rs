@@ -5,77 +5,87 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+mod gateway;
+
 use std::{
     fs,
-    io::Read,
-    path::Path,
-    process::{Command, Stdio},
-    sync::Mutex,
+    path::{Path, PathBuf},
+    process::Stdio,
 };
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State};
+use tokio::io::{AsyncBufReadExt, BufReader};
 use anyhow::{anyhow, Result};
+use counsel_ai_tauri::download::{download_verified, DownloadRequest, Progress, PROGRESS_STEP};
+use counsel_ai_tauri::inventory::{self, LocalModel, ModelStatus};
+use counsel_ai_tauri::model_registry::{
+    compute_sha256, load_verified_registry, registry_dir, TrustedModel,
+};
+use gateway::{GatewayStatus, GatewaySupervisor, LogLine};
 
-/// Track MCP gateway runtime state
-static MCP_STATUS: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
-
-/// --- Registry Structures ---
-#[derive(Deserialize)]
-struct TrustedRegistry {
-    registry_version: u32,
-    issued_at: String,
-    expires_at: String,
-    models: Vec<TrustedModel>,
+/// --- App Data Locations ---
+/// Models and the gateway `.env` live in the per-user app data directory,
+/// never relative to the process working directory.
+struct AppPaths {
+    data_dir: PathBuf,
+    models_dir: PathBuf,
+    env_file: PathBuf,
 }
 
-#[derive(Deserialize)]
-struct TrustedModel {
-    id: String,
-    provider: String,
-    license: String,
-    sha256: String,
-    uri: String,
+fn app_paths(app: &AppHandle) -> Result<AppPaths> {
+    let data_dir = app.path().app_data_dir()?;
+    let models_dir = data_dir.join("models");
+    fs::create_dir_all(&models_dir)?;
+    Ok(AppPaths {
+        models_dir,
+        env_file: data_dir.join(".env"),
+        data_dir,
+    })
 }
 
-/// --- Data Structures for Model Management ---
-#[derive(Serialize, Deserialize)]
-struct ModelInfo {
-    name: String,
-    file: String,
-    installed: bool,
-    path: String,
+/// Current `LOCAL_MODEL_PATH` from the app's `.env`, if set.
+fn active_model_path(env_file: &Path) -> Option<PathBuf> {
+    fs::read_to_string(env_file)
+        .ok()?
+        .lines()
+        .find_map(|l| l.strip_prefix("LOCAL_MODEL_PATH="))
+        .map(|v| PathBuf::from(v.trim()))
 }
 
 /// --- Gateway Controls ---
+/// The gateway runs from the app data directory so it loads the app's `.env`
+/// (including `LOCAL_MODEL_PATH`); see [`gateway::GatewaySupervisor`].
 #[tauri::command]
-async fn start_mcp_gateway(_app: AppHandle) -> Result<String, String> {
-    let mut lock = MCP_STATUS.lock().map_err(|_| "Status lock poisoned".to_string())?;
-    if *lock {
-        return Ok("MCP Gateway already running".into());
-    }
+async fn start_mcp_gateway(
+    app: AppHandle,
+    gateway: State<'_, GatewaySupervisor>,
+) -> Result<GatewayStatus, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    Ok(gateway.start(app, paths.data_dir).await)
+}
 
-    let spawn_result = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(["/C", "mcp-gateway.exe"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    } else {
-        Command::new("mcp-gateway")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    };
+#[tauri::command]
+async fn stop_mcp_gateway(gateway: State<'_, GatewaySupervisor>) -> Result<GatewayStatus, String> {
+    Ok(gateway.stop().await)
+}
 
-    match spawn_result {
-        Ok(_) => {
-            *lock = true;
-            Ok("MCP Gateway started successfully".into())
-        }
-        Err(e) => Err(format!("Failed to start gateway: {}", e)),
-    }
+#[tauri::command]
+async fn restart_mcp_gateway(
+    app: AppHandle,
+    gateway: State<'_, GatewaySupervisor>,
+) -> Result<GatewayStatus, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    gateway.stop().await;
+    Ok(gateway.start(app, paths.data_dir).await)
+}
+
+#[tauri::command]
+fn gateway_status(gateway: State<'_, GatewaySupervisor>) -> GatewayStatus {
+    gateway.status()
+}
+
+#[tauri::command]
+fn gateway_logs(gateway: State<'_, GatewaySupervisor>) -> Vec<LogLine> {
+    gateway.logs()
 }
 
 /// --- Version Info ---
@@ -85,44 +95,75 @@ fn app_version() -> String {
 }
 
 /// --- Local Model Listing ---
+/// Every model in the signed registry with its install, verification and active state,
+/// followed by any untrusted GGUF files found in the models folder.
 #[tauri::command]
-fn list_local_models() -> Vec<ModelInfo> {
-    vec![
-        ModelInfo {
-            name: "Mistral-7B-Instruct-Q4_K_M".into(),
-            file: "mistral-7b-instruct.Q4_K_M.gguf".into(),
-            installed: Path::new("services/mcp-gateway/models/mistral-7b-instruct.Q4_K_M.gguf")
-                .exists(),
-            path: "services/mcp-gateway/models/mistral-7b-instruct.Q4_K_M.gguf".into(),
-        },
-        ModelInfo {
-            name: "Phi-3-Mini-Instruct-Q4_K_M".into(),
-            file: "phi-3-mini-4k-instruct.Q4_K_M.gguf".into(),
-            installed: Path::new("services/mcp-gateway/models/phi-3-mini-4k-instruct.Q4_K_M.gguf")
-                .exists(),
-            path: "services/mcp-gateway/models/phi-3-mini-4k-instruct.Q4_K_M.gguf".into(),
-        },
-    ]
+fn list_local_models(app: AppHandle) -> Result<Vec<LocalModel>, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    let registry = load_verified_registry(registry_dir())
+        .map_err(|e| format!("Trusted registry rejected: {e}"))?;
+    let active = active_model_path(&paths.env_file);
+    inventory::scan(&registry, &paths.models_dir, active.as_deref()).map_err(|e| e.to_string())
 }
 
-/// --- Install Models via External Script ---
+/// --- Hash an Installed Model and Cache the Result ---
 #[tauri::command]
-async fn install_model(model_name: String) -> Result<String, String> {
-    let status = Command::new("cargo")
-        .args(["run", "--release", "--bin", "model-manager"])
-        .status()
+async fn verify_model(app: AppHandle, model_id: String) -> Result<ModelStatus, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let registry = load_verified_registry(registry_dir())?;
+        let model = registry
+            .find(&model_id)
+            .ok_or_else(|| anyhow!("Model {model_id} not listed in trusted registry"))?;
+        inventory::verify_installed(model, &paths.models_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// --- Install Models via model-manager ---
+/// Runs `model-manager install <id>` and forwards each JSON progress line to the UI
+/// as a `model-install-progress` event.
+#[tauri::command]
+async fn install_model(app: AppHandle, model_id: String) -> Result<String, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    let mut child = tokio::process::Command::new("model-manager")
+        .arg("--models-dir")
+        .arg(&paths.models_dir)
+        .arg("--env-file")
+        .arg(&paths.env_file)
+        .args(["install", &model_id])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
         .map_err(|e| format!("Failed to run model-manager: {e}"))?;
 
+    let stdout = child.stdout.take().ok_or("model-manager stdout unavailable")?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut last_error = None;
+    while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if event["event"] == "error" {
+            last_error = event["message"].as_str().map(str::to_string);
+        }
+        app.emit("model-install-progress", &event).map_err(|e| e.to_string())?;
+    }
+
+    let status = child.wait().await.map_err(|e| e.to_string())?;
     if !status.success() {
-        return Err("Model installation failed".into());
+        return Err(last_error.unwrap_or_else(|| "Model installation failed".into()));
     }
-    Ok(format!("✅ {} installed successfully", model_name))
+    Ok(format!("✅ {} installed successfully", model_id))
 }
 
 /// --- Update Active Model in .env ---
 #[tauri::command]
-fn set_active_model(model_path: String) -> Result<String, String> {
-    let env_path = Path::new(".env");
+fn set_active_model(app: AppHandle, model_path: String) -> Result<String, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    let env_path = paths.env_file.as_path();
     let content = fs::read_to_string(env_path).unwrap_or_default();
     let updated = if content.contains("LOCAL_MODEL_PATH=") {
         content
@@ -147,132 +188,127 @@ fn set_active_model(model_path: String) -> Result<String, String> {
 
 /// --- Verify Active Model from Trusted Registry ---
 #[tauri::command]
-fn verify_active_model() -> Result<String, String> {
-    verify_or_repair_model_from_registry().map_err(|e| format!("Verification failed: {e}"))
+fn verify_active_model(app: AppHandle) -> Result<String, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    verify_or_repair_model_from_registry(&app, &paths).map_err(|e| format!("Verification failed: {e}"))
 }
 
 /// --- Lightweight background verification (hash-only, non-repairing) ---
 #[tauri::command]
-fn quick_verify_model() -> Result<String, String> {
-    let env_path = Path::new(".env");
-    let content = fs::read_to_string(env_path).unwrap_or_default();
-    let model_line = content
-        .lines()
-        .find(|l| l.starts_with("LOCAL_MODEL_PATH="))
-        .map(|l| l.replacen("LOCAL_MODEL_PATH=", "", 1).trim().to_string());
-
-    let Some(path) = model_line else {
+fn quick_verify_model(app: AppHandle) -> Result<String, String> {
+    let paths = app_paths(&app).map_err(|e| e.to_string())?;
+    let Some(path) = active_model_path(&paths.env_file) else {
         return Err("LOCAL_MODEL_PATH not found in .env".into());
     };
 
-    if !Path::new(&path).exists() {
-        return Err(format!("Model file not found: {}", path));
+    if !path.exists() {
+        return Err(format!("Model file not found: {}", path.display()));
     }
 
-    let mut file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
-    let mut hasher = Sha256::new();
-    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
-    let hash = format!("{:x}", hasher.finalize());
-
+    let hash = compute_sha256(&path).map_err(|e| e.to_string())?;
     Ok(format!("✅ Model SHA256: {}", hash))
 }
 
 /// --- Helper: Full verification or repair via registry ---
-fn verify_or_repair_model_from_registry() -> Result<String> {
-    let registry_path = Path::new("services/mcp-gateway/models/trusted_models.json");
-    let registry_data = fs::read_to_string(registry_path)
-        .map_err(|_| anyhow!("trusted_models.json not found"))?;
-    let registry: TrustedRegistry = serde_json::from_str(&registry_data)?;
+fn verify_or_repair_model_from_registry(app: &AppHandle, paths: &AppPaths) -> Result<String> {
+    let registry = load_verified_registry(registry_dir())?;
 
-    let env_path = Path::new(".env");
-    let env_content = fs::read_to_string(env_path).unwrap_or_default();
-    let active_path = env_content
-        .lines()
-        .find(|l| l.starts_with("LOCAL_MODEL_PATH="))
-        .map(|l| l.replacen("LOCAL_MODEL_PATH=", "", 1).trim().to_string())
+    let active_path = active_model_path(&paths.env_file)
         .ok_or_else(|| anyhow!("LOCAL_MODEL_PATH not set in .env"))?;
 
-    let file_name = Path::new(&active_path)
+    let file_name = active_path
         .file_name()
         .and_then(|f| f.to_str())
         .ok_or_else(|| anyhow!("Invalid model path"))?;
 
-    let Some(model) = registry.models.iter().find(|m| m.id.ends_with(file_name)) else {
+    let Some(model) = registry.find(file_name) else {
         return Err(anyhow!("Model not listed in trusted registry"));
     };
+    let model_path = paths.models_dir.join(&model.file);
 
-    if !Path::new(&active_path).exists() {
-        println!("⚠️ Missing model — fetching from registry source");
-        download_model(&active_path, &model.uri)?;
+    if !model_path.exists() {
+        let hash = download_model(app, &model_path, model)?;
+        inventory::verify_installed(model, &paths.models_dir)?;
+        return Ok(format!("🛠️ Model downloaded and verified ({} — {})", model.id, hash));
     }
 
-    let computed = compute_sha256(&active_path)?;
-    if computed != model.sha256 {
-        println!("⚠️ Hash mismatch — repairing from registry URL");
-        fs::remove_file(&active_path).ok();
-        download_model(&active_path, &model.uri)?;
-        let rehash = compute_sha256(&active_path)?;
-        if rehash != model.sha256 {
-            return Err(anyhow!("Checksum still invalid after redownload"));
-        }
+    if inventory::verify_installed(model, &paths.models_dir)? == ModelStatus::Corrupt {
+        fs::remove_file(&model_path).ok();
+        let rehash = download_model(app, &model_path, model)?;
+        inventory::verify_installed(model, &paths.models_dir)?;
         return Ok(format!(
             "🛠️ Model repaired and verified ({} — {})",
             model.id, rehash
         ));
     }
 
-    Ok(format!("✅ Model verified successfully ({} — {})", model.id, computed))
-}
-
-/// --- Utility: Compute SHA256 hash ---
-fn compute_sha256<P: AsRef<Path>>(path: P) -> Result<String> {
-    let mut file = std::fs::File::open(&path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 8192];
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(format!("✅ Model verified successfully ({} — {})", model.id, model.sha256))
 }
 
 /// --- Utility: Download model if missing or corrupted ---
-fn download_model(path: &str, url: &str) -> Result<()> {
-    if url.is_empty() {
+/// Resumes any previous `.part` file and only moves it into place once the hash matches.
+/// Progress goes to the UI as `model-install-progress` events shaped like model-manager's.
+fn download_model(app: &AppHandle, path: &Path, model: &TrustedModel) -> Result<String> {
+    if model.uri.is_empty() {
         return Err(anyhow!("Unknown model URL"));
     }
-    let tmp = format!("{}.part", path);
-    println!("⬇️ Downloading from registry source...");
-    let status = Command::new("curl")
-        .args(["-L", url, "-o", &tmp])
-        .status()
-        .map_err(|e| anyhow!("curl not available: {e}"))?;
-    if !status.success() {
-        return Err(anyhow!("Download failed"));
-    }
-    fs::rename(&tmp, &path)?;
-    Ok(())
+    let emit = |event: serde_json::Value| {
+        // A closed window must not abort the download.
+        app.emit("model-install-progress", event).ok();
+    };
+    emit(serde_json::json!({ "event": "stage", "id": model.id, "stage": "downloading" }));
+    let mut last_reported = None;
+    let hash = download_verified(
+        &DownloadRequest {
+            url: &model.uri,
+            dest: path,
+            sha256: &model.sha256,
+            expected_size: Some(model.size_bytes),
+        },
+        |Progress { downloaded, total }| {
+            let due = match last_reported {
+                Some(last) => downloaded >= last + PROGRESS_STEP,
+                None => true,
+            };
+            if due || total == Some(downloaded) {
+                last_reported = Some(downloaded);
+                emit(serde_json::json!({
+                    "event": "progress",
+                    "id": model.id,
+                    "downloaded": downloaded,
+                    "total": total,
+                }));
+            }
+        },
+    )?;
+    emit(serde_json::json!({ "event": "verified", "id": model.id, "sha256": &hash }));
+    Ok(hash)
 }
 
 /// --- App Entry Point ---
 fn main() {
     tauri::Builder::default()
+        .manage(GatewaySupervisor::default())
         .invoke_handler(tauri::generate_handler![
             start_mcp_gateway,
+            stop_mcp_gateway,
+            restart_mcp_gateway,
+            gateway_status,
+            gateway_logs,
             app_version,
             verify_active_model,
             quick_verify_model,
             list_local_models,
+            verify_model,
             install_model,
             set_active_model
         ])
-        .setup(|_| {
-            println!("⚖️ Counsel AI Desktop initialized");
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running Counsel AI desktop");
+        .build(tauri::generate_context!())
+        .expect("error while building Counsel AI desktop")
+        .run(|app, event| {
+            // Never leave an orphaned gateway behind when the app quits.
+            if let RunEvent::Exit = event {
+                tauri::async_runtime::block_on(app.state::<GatewaySupervisor>().stop());
+            }
+        });
 }
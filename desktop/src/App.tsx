@@ -23,12 +23,12 @@ export function App() {
       setLoading(true);
 
       try {
-        const endpoint = offlineMode ? '/reason_local' : '/reason';
-        const { data: reasonReq } = await axios.post(`${API_URL}/query`, { text: query });
+        const endpoint = offlineMode ? '/v1/reason_local' : '/v1/reason';
+        const { data: reasonReq } = await axios.post(`${API_URL}/v1/query`, { text: query });
         const { data: reasonRes } = await axios.post(`${API_URL}${endpoint}`, reasonReq);
 
         setMessages((prev) => [...prev, { role: 'assistant', text: reasonRes.summary }]);
-        await axios.post(`${API_URL}/store`, reasonRes);
+        await axios.post(`${API_URL}/v1/store`, reasonRes);
       } catch (error) {
         const message = error instanceof Error ? error.message : 'Unknown error';
         setMessages((prev) => [
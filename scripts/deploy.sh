@@ -181,7 +181,7 @@ wait_for_services() {
     local attempt=1
     
     while [[ $attempt -le $max_attempts ]]; do
-        if curl -f http://localhost:5142/health &> /dev/null; then
+        if curl -f http://localhost:5142/startupz &> /dev/null; then
             log_success "MCP Gateway is ready"
             break
         fi
@@ -203,7 +203,7 @@ run_health_checks() {
     log_info "Running health checks..."
     
     # Check MCP Gateway
-    if curl -f http://localhost:5142/health &> /dev/null; then
+    if curl -f http://localhost:5142/readyz &> /dev/null; then
         log_success "MCP Gateway health check passed"
     else
         log_error "MCP Gateway health check failed"
@@ -1,112 +1,346 @@
 //! Counsel AI — Local Model Manager
 //! Utility for downloading, verifying, and activating local LLMs.
+//!
+//! Models are taken from the signed `trusted_models.json` registry and selected by id.
+//! Every command prints one JSON event per line on stdout so the desktop app can
+//! drive installs and render progress:
+//!
+//! ```text
+//! model-manager [--registry-dir DIR] [--models-dir DIR] [--env-file PATH] <command>
+//!
+//!   list              list registry models, their metadata and install state
+//!   install <id>      download, verify and install a model
+//!   verify [id]       re-hash one model (or every installed model)
+//!   remove <id>       delete an installed model
+//!   activate <id>     point LOCAL_MODEL_PATH at an installed, verified model
+//! ```
 
-use std::fs::{self, File};
-use std::io::{self, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use sha2::{Digest, Sha256};
+use anyhow::{anyhow, bail, Context, Result};
+use counsel_ai_tauri::download::{download_verified, DownloadRequest, Progress, PROGRESS_STEP};
+use counsel_ai_tauri::inventory::{self, ModelStatus, VerificationCache};
+use counsel_ai_tauri::model_registry::{
+    load_verified_registry, registry_dir, ModelRole, Quantization, TrustedModel, TrustedRegistry,
+};
+use serde::Serialize;
 
-#[derive(Debug)]
-struct Model {
-    name: &'static str,
-    url: &'static str,
-    sha256: &'static str,
-    file: &'static str,
-}
+const DEFAULT_MODELS_DIR: &str = "services/mcp-gateway/models";
+const DEFAULT_ENV_FILE: &str = ".env";
 
-const MODELS: &[Model] = &[
-    Model {
-        name: "Mistral-7B-Instruct-Q4_K_M",
-        url: "https://huggingface.co/TheBloke/Mistral-7B-Instruct-v0.2-GGUF/resolve/main/mistral-7b-instruct-v0.2.Q4_K_M.gguf",
-        sha256: "c9b84e2cb9d5e547faefab7b9b2a8cc73e2e9ab31dd23842fbbfc97b5670a708",
-        file: "mistral-7b-instruct.Q4_K_M.gguf",
+/// Machine-readable progress events, one JSON object per stdout line.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Registry {
+        version: u32,
+        expires_at: &'a str,
     },
     Model {
-        name: "Phi-3-Mini-Instruct-Q4_K_M",
-        url: "https://huggingface.co/TheBloke/phi-3-mini-4k-instruct-GGUF/resolve/main/phi-3-mini-4k-instruct.Q4_K_M.gguf",
-        sha256: "a21ad4c26f53211e39df6b374f640093226f55da16d3f7a7c10c3a90ab5c04b2",
-        file: "phi-3-mini-4k-instruct.Q4_K_M.gguf",
+        id: &'a str,
+        name: &'a str,
+        file: &'a str,
+        size_bytes: u64,
+        context_length: u32,
+        quantization: Quantization,
+        role: ModelRole,
+        min_ram_bytes: u64,
+        status: ModelStatus,
+        active: bool,
+        path: String,
+    },
+    Stage {
+        id: &'a str,
+        stage: Stage,
+    },
+    Progress {
+        id: &'a str,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    Verified {
+        id: &'a str,
+        sha256: String,
+    },
+    Installed {
+        id: &'a str,
+        path: String,
+    },
+    Removed {
+        id: &'a str,
+        path: String,
     },
-];
+    Activated {
+        id: &'a str,
+        path: String,
+        env_file: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Stage {
+    Downloading,
+    Verifying,
+}
+
+fn emit(event: &Event<'_>) {
+    // Serialization of these plain structs cannot fail.
+    println!("{}", serde_json::to_string(event).expect("event serializes"));
+}
 
-fn main() -> Result<()> {
-    println!("⚖️  Counsel AI Model Manager");
-    println!("Available models:");
-    for (i, m) in MODELS.iter().enumerate() {
-        println!("  [{}] {} ", i + 1, m.name);
+#[derive(Debug)]
+enum Action {
+    List,
+    Install(String),
+    Verify(Option<String>),
+    Remove(String),
+    Activate(String),
+}
+
+#[derive(Debug)]
+struct Options {
+    registry_dir: PathBuf,
+    models_dir: PathBuf,
+    env_file: PathBuf,
+    action: Action,
+}
+
+const USAGE: &str = "usage: model-manager [--registry-dir DIR] [--models-dir DIR] [--env-file PATH] \
+                     <list | install <id> | verify [id] | remove <id> | activate <id>>";
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options> {
+    let mut registry_dir = registry_dir();
+    let mut models_dir = std::env::var("COUNSEL_MODELS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_MODELS_DIR));
+    let mut env_file = PathBuf::from(DEFAULT_ENV_FILE);
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| anyhow!("{flag} requires a value"));
+        match arg.as_str() {
+            "--registry-dir" => registry_dir = PathBuf::from(value("--registry-dir")?),
+            "--models-dir" => models_dir = PathBuf::from(value("--models-dir")?),
+            "--env-file" => env_file = PathBuf::from(value("--env-file")?),
+            flag if flag.starts_with("--") => bail!("unknown option {flag}\n{USAGE}"),
+            _ => positional.push(arg),
+        }
     }
 
-    print!("Select model number to install: ");
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let choice: usize = input.trim().parse().unwrap_or(0);
-    if choice == 0 || choice > MODELS.len() {
-        println!("❌ Invalid choice");
-        return Ok(());
+    let mut positional = positional.into_iter();
+    let command = positional.next().ok_or_else(|| anyhow!(USAGE))?;
+    let id = positional.next();
+    let require_id = |id: Option<String>| id.ok_or_else(|| anyhow!("{command} requires a model id\n{USAGE}"));
+    let action = match command.as_str() {
+        "list" => Action::List,
+        "install" => Action::Install(require_id(id)?),
+        "verify" => Action::Verify(id),
+        "remove" => Action::Remove(require_id(id)?),
+        "activate" => Action::Activate(require_id(id)?),
+        other => bail!("unknown command {other}\n{USAGE}"),
+    };
+    if let Some(extra) = positional.next() {
+        bail!("unexpected argument {extra}\n{USAGE}");
     }
 
-    let model = &MODELS[choice - 1];
-    let models_dir = PathBuf::from("services/mcp-gateway/models");
-    fs::create_dir_all(&models_dir)?;
-
-    let model_path = models_dir.join(model.file);
-    let tmp_path = model_path.with_extension("download");
-
-    println!("⬇️  Downloading {} ...", model.name);
-    let status = Command::new("curl")
-        .args(["-L", model.url, "-o"])
-        .arg(&tmp_path)
-        .status()
-        .expect("curl required");
-    if !status.success() {
-        anyhow::bail!("Download failed");
+    Ok(Options {
+        registry_dir,
+        models_dir,
+        env_file,
+        action,
+    })
+}
+
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(run);
+    if let Err(err) = result {
+        emit(&Event::Error {
+            message: format!("{err:#}"),
+        });
+        std::process::exit(1);
     }
+}
+
+fn run(opts: Options) -> Result<()> {
+    let registry = load_verified_registry(&opts.registry_dir)
+        .context("trusted model registry failed verification")?;
+    emit(&Event::Registry {
+        version: registry.registry_version,
+        expires_at: &registry.expires_at,
+    });
 
-    println!("🔍 Verifying SHA256 checksum ...");
-    let mut file = File::open(&tmp_path)?;
-    let mut hasher = Sha256::new();
-    io::copy(&mut file, &mut hasher)?;
-    let hash = format!("{:x}", hasher.finalize());
-
-    if hash != model.sha256 {
-        anyhow::bail!(
-            "Checksum mismatch!\nExpected: {}\nFound:    {}",
-            model.sha256,
-            hash
-        );
+    match &opts.action {
+        Action::List => list(&registry, &opts),
+        Action::Install(id) => install(lookup(&registry, id)?, &opts),
+        Action::Verify(Some(id)) => verify(lookup(&registry, id)?, &opts),
+        Action::Verify(None) => registry
+            .models
+            .iter()
+            .filter(|m| model_path(m, &opts).exists())
+            .try_for_each(|m| verify(m, &opts)),
+        Action::Remove(id) => remove(lookup(&registry, id)?, &opts),
+        Action::Activate(id) => activate(lookup(&registry, id)?, &opts),
     }
+}
 
-    fs::rename(&tmp_path, &model_path)
-        .with_context(|| "Failed to move model into place")?;
-    println!("✅ Model verified and installed at {:?}", model_path);
-
-    // Update .env
-    let env_path = PathBuf::from(".env");
-    let env_content = fs::read_to_string(&env_path).unwrap_or_default();
-    let new_line = format!("LOCAL_MODEL_PATH={}\n", model_path.display());
-    let updated = if env_content.contains("LOCAL_MODEL_PATH") {
-        env_content
-            .lines()
-            .map(|l| {
-                if l.starts_with("LOCAL_MODEL_PATH=") {
-                    new_line.trim_end().to_string()
-                } else {
-                    l.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-            + "\n"
-    } else {
-        env_content + &new_line
-    };
-    fs::write(&env_path, updated)?;
+fn lookup<'a>(registry: &'a TrustedRegistry, id: &str) -> Result<&'a TrustedModel> {
+    let model = registry
+        .find(id)
+        .ok_or_else(|| anyhow!("model {id} is not listed in the trusted registry"))?;
+    if !model.trusted {
+        bail!("model {id} is marked untrusted in the registry");
+    }
+    Ok(model)
+}
+
+fn model_path(model: &TrustedModel, opts: &Options) -> PathBuf {
+    opts.models_dir.join(&model.file)
+}
+
+fn list(registry: &TrustedRegistry, opts: &Options) -> Result<()> {
+    let active = read_env_value(&opts.env_file, "LOCAL_MODEL_PATH").map(PathBuf::from);
+    let local = inventory::scan(registry, &opts.models_dir, active.as_deref())?;
+    for model in &registry.models {
+        let Some(state) = local.iter().find(|l| l.id.as_deref() == Some(model.id.as_str())) else {
+            continue;
+        };
+        emit(&Event::Model {
+            id: &model.id,
+            name: &model.name,
+            file: &model.file,
+            size_bytes: model.size_bytes,
+            context_length: model.context_length,
+            quantization: model.quantization,
+            role: model.role,
+            min_ram_bytes: model.min_ram_bytes,
+            status: state.status,
+            active: state.active,
+            path: state.path.display().to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn install(model: &TrustedModel, opts: &Options) -> Result<()> {
+    fs::create_dir_all(&opts.models_dir)
+        .with_context(|| format!("failed to create {}", opts.models_dir.display()))?;
+
+    let model_path = model_path(model, opts);
+
+    emit(&Event::Stage {
+        id: &model.id,
+        stage: Stage::Downloading,
+    });
+    let mut last_reported = None;
+    let hash = download_verified(
+        &DownloadRequest {
+            url: &model.uri,
+            dest: &model_path,
+            sha256: &model.sha256,
+            expected_size: Some(model.size_bytes),
+        },
+        |Progress { downloaded, total }| {
+            let due = match last_reported {
+                Some(last) => downloaded >= last + PROGRESS_STEP,
+                None => true,
+            };
+            if due || total == Some(downloaded) {
+                last_reported = Some(downloaded);
+                emit(&Event::Progress {
+                    id: &model.id,
+                    downloaded,
+                    total,
+                });
+            }
+        },
+    )?;
+    let mut cache = VerificationCache::load(&opts.models_dir);
+    cache.record(&model.file, &fs::metadata(&model_path)?, hash.clone());
+    cache.save(&opts.models_dir)?;
+    emit(&Event::Verified {
+        id: &model.id,
+        sha256: hash,
+    });
+
+    emit(&Event::Installed {
+        id: &model.id,
+        path: model_path.display().to_string(),
+    });
+    Ok(())
+}
+
+fn verify(model: &TrustedModel, opts: &Options) -> Result<()> {
+    let path = model_path(model, opts);
+    if !path.exists() {
+        bail!("model {} is not installed at {}", model.id, path.display());
+    }
 
-    println!("🔧 .env updated → LOCAL_MODEL_PATH={}", model_path.display());
-    println!("🎯 You can now restart the MCP Gateway to use {}", model.name);
+    emit(&Event::Stage {
+        id: &model.id,
+        stage: Stage::Verifying,
+    });
+    match inventory::verify_installed(model, &opts.models_dir)? {
+        ModelStatus::Verified => {}
+        ModelStatus::Corrupt => bail!("model {} is corrupt: SHA-256 does not match the registry", model.id),
+        status => bail!("model {} could not be verified ({status:?})", model.id),
+    }
+    emit(&Event::Verified {
+        id: &model.id,
+        sha256: model.sha256.clone(),
+    });
+    Ok(())
+}
+
+fn remove(model: &TrustedModel, opts: &Options) -> Result<()> {
+    let path = model_path(model, opts);
+    fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))?;
+    emit(&Event::Removed {
+        id: &model.id,
+        path: path.display().to_string(),
+    });
+    Ok(())
+}
+
+fn activate(model: &TrustedModel, opts: &Options) -> Result<()> {
+    verify(model, opts)?;
+
+    let path = model_path(model, opts);
+    set_env_value(&opts.env_file, "LOCAL_MODEL_PATH", &path.display().to_string())?;
+    emit(&Event::Activated {
+        id: &model.id,
+        path: path.display().to_string(),
+        env_file: opts.env_file.display().to_string(),
+    });
     Ok(())
 }
+
+/// Value of `key=...` in a dotenv file, if present.
+fn read_env_value(env_path: &Path, key: &str) -> Option<String> {
+    let prefix = format!("{key}=");
+    fs::read_to_string(env_path)
+        .ok()?
+        .lines()
+        .find_map(|l| l.strip_prefix(&prefix).map(|v| v.trim().to_string()))
+}
+
+/// Replace `key=...` in a dotenv file, appending it when absent.
+fn set_env_value(env_path: &Path, key: &str, value: &str) -> Result<()> {
+    let content = fs::read_to_string(env_path).unwrap_or_default();
+    let prefix = format!("{key}=");
+    let new_line = format!("{key}={value}");
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    match lines.iter_mut().find(|l| l.starts_with(&prefix)) {
+        Some(line) => *line = new_line,
+        None => lines.push(new_line),
+    }
+
+    fs::write(env_path, lines.join("\n") + "\n")
+        .with_context(|| format!("failed to update {}", env_path.display()))
+}
@@ -1,47 +1,57 @@
 use axum::{
-    extract::Request,
-    http::{header::AUTHORIZATION, StatusCode},
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap},
     middleware::Next,
     response::Response,
 };
-use std::env;
+
+use crate::{config::Config, error::ApiError, state::AppState};
+
+/// Principal of the main `api_key`.
+pub const DEFAULT_PRINCIPAL: &str = "default";
+
+/// The authenticated caller, added to the request extensions by [`auth_middleware`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+/// The principal whose key is the bearer token in `headers`: `default` for `api_key`,
+/// otherwise the name of the matching `api_keys` entry
+pub fn principal(config: &Config, headers: &HeaderMap) -> Option<Principal> {
+    let api_key = headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())?
+        .strip_prefix("Bearer ")?;
+
+    if api_key == config.api_key {
+        return Some(Principal(DEFAULT_PRINCIPAL.to_string()));
+    }
+    config
+        .api_keys
+        .iter()
+        .filter_map(|entry| entry.split_once('='))
+        .find(|(_, key)| *key == api_key)
+        .map(|(name, _)| Principal(name.to_string()))
+}
 
 /// Simple API key authentication middleware
 pub async fn auth_middleware(
+    State(state): State<AppState>,
     mut req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    // Skip auth for health endpoint
-    if req.uri().path() == "/health" {
+) -> Result<Response, ApiError> {
+    // Skip auth for probes, `/version` and the API docs, and for the Prometheus scrape
+    // endpoint only when `metrics_public` opts in
+    let path = req.uri().path();
+    if matches!(path, "/health" | "/livez" | "/readyz" | "/startupz" | "/version" | "/api-docs/openapi.json")
+        || (path == "/metrics" && state.config.get().metrics_public)
+        || path == "/docs"
+        || path.starts_with("/docs/")
+    {
         return Ok(next.run(req).await);
     }
 
-    // Get API key from environment
-    let expected_api_key = env::var("API_KEY").unwrap_or_else(|_| "counsel-ai-dev-key".to_string());
-    
-    // Extract API key from Authorization header
-    let auth_header = req
-        .headers()
-        .get(AUTHORIZATION)
-        .and_then(|header| header.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    // Check if it's a Bearer token
-    if !auth_header.starts_with("Bearer ") {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
-    let api_key = &auth_header[7..]; // Remove "Bearer " prefix
-    
-    if api_key != expected_api_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    let principal = principal(&state.config.get(), req.headers()).ok_or(ApiError::Unauthorized)?;
+    req.extensions_mut().insert(principal);
 
     Ok(next.run(req).await)
 }
-
-/// Generate a secure API key
-pub fn generate_api_key() -> String {
-    use uuid::Uuid;
-    format!("counsel-ai-{}", Uuid::new_v4().to_string().replace('-', ""))
-}
\ No newline at end of file
@@ -0,0 +1,447 @@
+//! Structure-aware chunking of Italian legal texts. Recognises code articles with their
+//! commi and lettere, the sections and motivi of Cassazione decisions, and numbered
+//! contract clauses. Chunks follow that structure: articles, clauses and motivi are
+//! never merged, and a unit is only split (first into its parts, then at sentence
+//! ends) when it exceeds the size limit. Every chunk carries its structural path and
+//! its byte span in the source.
+
+use std::{ops::Range, sync::LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+
+/// Default chunk size for retrieval and prompt context, in bytes.
+pub const MAX_CHUNK_BYTES: usize = 1500;
+/// Default `context_document_bytes`: the context budget for each document or file referenced
+/// by a query, in bytes.
+pub const DOCUMENT_CONTEXT_BYTES: usize = 12_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chunk {
+    /// Structural location, outermost first, e.g. `["Art. 2043", "comma 1", "lett. a)"]`;
+    /// empty for text outside any recognised structure
+    pub path: Vec<String>,
+    pub text: String,
+    /// Byte range of `text` in the source
+    pub span: Range<usize>,
+}
+
+impl Chunk {
+    pub fn heading(&self) -> String {
+        self.path.join(" › ")
+    }
+}
+
+static SECTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(massim[ae]|fatt[oi] di causa|svolgimento del processo|fatto|diritto|motivi della decisione|ragioni della decisione|p\.\s*q\.\s*m\.?)\s*[:.]?$",
+    )
+    .unwrap()
+});
+static ARTICLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(?:art\.|articolo)\s*(\d+)(?:[\s-]*(bis|ter|quater|quinquies|sexies|septies|octies|novies|decies))?\b\s*(.*)$",
+    )
+    .unwrap()
+});
+static CLAUSE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^clausola\s+(\d+)\b\s*(.*)$").unwrap());
+static NUMBERED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:(\d{1,3}(?:\.\d{1,3})+)\.?|(\d{1,3})[.)])\s+\S").unwrap());
+static LETTER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([a-z])\)\s+\S").unwrap());
+/// Sentence ends: a full stop, semicolon or colon followed by whitespace.
+static SENTENCE_END: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[.;:]\s+").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Root,
+    /// Massima, motivi, P.Q.M. … of a decision.
+    Section,
+    /// Code article or contract article/clause.
+    Article,
+    /// Numbered comma, motivo or clause; `depth` counts the dots in `2.1.3`.
+    Numbered { depth: usize },
+    /// Unnumbered paragraph of an article.
+    Comma,
+    Letter,
+}
+
+impl Kind {
+    fn level(self) -> usize {
+        match self {
+            Kind::Root => 0,
+            Kind::Section => 1,
+            Kind::Article => 2,
+            Kind::Comma => 3,
+            Kind::Numbered { depth } => 3 + depth,
+            Kind::Letter => usize::MAX,
+        }
+    }
+
+    /// Units that are always chunked on their own.
+    fn is_container(self) -> bool {
+        matches!(self, Kind::Root | Kind::Section)
+    }
+}
+
+struct Node {
+    kind: Kind,
+    label: String,
+    start: usize,
+    /// End of the last non-blank line in the subtree.
+    end: usize,
+    children: Vec<usize>,
+}
+
+/// Split `text` into structural chunks of at most `max_bytes` (whole sentences permitting).
+pub fn chunk(text: &str, max_bytes: usize) -> Vec<Chunk> {
+    let nodes = parse(text);
+    let mut chunks = Vec::new();
+    emit(text, &nodes, 0, &mut Vec::new(), max_bytes.max(1), &mut chunks);
+    chunks
+}
+
+fn parse(text: &str) -> Vec<Node> {
+    let mut nodes = vec![Node {
+        kind: Kind::Root,
+        label: String::new(),
+        start: 0,
+        end: 0,
+        children: Vec::new(),
+    }];
+    let mut stack = vec![0];
+    let mut after_blank = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            after_blank = true;
+            continue;
+        }
+        let line_start = start + (line.len() - line.trim_start().len());
+        let line_end = start + line.trim_end().len();
+
+        let heading = heading(trimmed);
+        let opened = match heading {
+            Some((kind, label)) => Some((kind, label)),
+            None => implicit_comma(&nodes, &stack, after_blank),
+        };
+        if let Some((kind, label)) = opened {
+            while nodes[*stack.last().unwrap()].kind.level() >= kind.level() {
+                stack.pop();
+            }
+            let parent = *stack.last().unwrap();
+            let label = match kind {
+                Kind::Numbered { .. } => numbered_label(&nodes, &stack, &label),
+                Kind::Comma => format!("comma {}", nodes[parent].children.len() + 1),
+                _ => label,
+            };
+            nodes.push(Node {
+                kind,
+                label,
+                start: line_start,
+                end: line_end,
+                children: Vec::new(),
+            });
+            let id = nodes.len() - 1;
+            nodes[parent].children.push(id);
+            stack.push(id);
+        }
+        for &open in &stack {
+            nodes[open].end = line_end;
+        }
+        after_blank = false;
+    }
+    nodes
+}
+
+/// The kind and label of a heading line.
+fn heading(line: &str) -> Option<(Kind, String)> {
+    if let Some(caps) = SECTION.captures(line) {
+        return Some((Kind::Section, section_label(&caps[1])));
+    }
+    if let Some(caps) = ARTICLE.captures(line) {
+        // "Art. 2043 c.c. dispone…" opening a sentence is a reference, not a heading:
+        // a heading line is the number alone or followed by a short title.
+        let rest = caps[3].trim();
+        let titled = rest.is_empty()
+            || (rest.len() <= 100 && rest.starts_with(['.', '-', '–', '—', ':', '(']));
+        if titled {
+            let suffix = caps.get(2).map(|s| format!("-{}", s.as_str().to_lowercase())).unwrap_or_default();
+            return Some((Kind::Article, format!("Art. {}{suffix}", &caps[1])));
+        }
+        return None;
+    }
+    if let Some(caps) = CLAUSE.captures(line) {
+        return Some((Kind::Article, format!("Clausola {}", &caps[1])));
+    }
+    if let Some(caps) = NUMBERED.captures(line) {
+        let number = caps.get(1).or(caps.get(2)).unwrap().as_str();
+        let depth = number.matches('.').count();
+        return Some((Kind::Numbered { depth }, number.to_string()));
+    }
+    if let Some(caps) = LETTER.captures(line) {
+        return Some((Kind::Letter, format!("lett. {})", &caps[1])));
+    }
+    None
+}
+
+fn section_label(heading: &str) -> String {
+    let heading = heading.to_lowercase();
+    if heading.starts_with('p') {
+        return "P.Q.M.".to_string();
+    }
+    let mut label = heading;
+    label[..1].make_ascii_uppercase();
+    label
+}
+
+/// Numbered units are commi inside an article, motivi in the reasoning of a decision,
+/// points elsewhere in a decision, and clauses in a contract.
+fn numbered_label(nodes: &[Node], stack: &[usize], number: &str) -> String {
+    let ancestors = || stack.iter().map(|&id| &nodes[id]);
+    if ancestors().any(|n| n.kind == Kind::Article) {
+        format!("comma {number}")
+    } else if let Some(section) = ancestors().find(|n| n.kind == Kind::Section) {
+        let reasoning = ["Motivi", "Ragioni", "Diritto"].iter().any(|s| section.label.starts_with(s));
+        if reasoning {
+            format!("motivo {number}")
+        } else {
+            format!("punto {number}")
+        }
+    } else {
+        format!("clausola {number}")
+    }
+}
+
+/// Text under an article without numbered commi: each paragraph is a comma.
+fn implicit_comma(nodes: &[Node], stack: &[usize], after_blank: bool) -> Option<(Kind, String)> {
+    let article = stack.iter().rposition(|&id| nodes[id].kind == Kind::Article)?;
+    let article = &nodes[stack[article]];
+    if article.children.iter().any(|&c| matches!(nodes[c].kind, Kind::Numbered { .. })) {
+        return None;
+    }
+    let top = &nodes[*stack.last().unwrap()];
+    let starts_comma = top.kind == Kind::Article || (after_blank && matches!(top.kind, Kind::Comma | Kind::Letter));
+    starts_comma.then(|| (Kind::Comma, String::new()))
+}
+
+fn emit(text: &str, nodes: &[Node], id: usize, path: &mut Vec<String>, max: usize, out: &mut Vec<Chunk>) {
+    let node = &nodes[id];
+    if !node.label.is_empty() {
+        path.push(node.label.clone());
+    }
+    let whole = trimmed(text, node.start..node.end);
+    if node.children.is_empty() || (!node.kind.is_container() && whole.len() <= max) {
+        split_at_sentences(text, whole, path, max, out);
+    } else {
+        // Text before the first part (title, intro), then each part.
+        let intro = trimmed(text, node.start..nodes[node.children[0]].start);
+        split_at_sentences(text, intro, path, max, out);
+        for &child in &node.children {
+            emit(text, nodes, child, path, max, out);
+        }
+    }
+    if !node.label.is_empty() {
+        path.pop();
+    }
+}
+
+/// Emit `span` as one chunk, or as consecutive runs of whole sentences when it is too long.
+fn split_at_sentences(text: &str, span: Range<usize>, path: &[String], max: usize, out: &mut Vec<Chunk>) {
+    let mut push = |range: Range<usize>| {
+        let range = trimmed(text, range);
+        if !range.is_empty() {
+            out.push(Chunk {
+                path: path.to_vec(),
+                text: text[range.clone()].to_string(),
+                span: range,
+            });
+        }
+    };
+    if span.len() <= max {
+        push(span);
+        return;
+    }
+
+    let body = &text[span.clone()];
+    let mut start = 0;
+    let mut last_end = 0;
+    let ends = SENTENCE_END.find_iter(body).map(|m| m.end()).chain([body.len()]);
+    for end in ends {
+        if end - start > max && last_end > start {
+            push(span.start + start..span.start + last_end);
+            start = last_end;
+        }
+        // A single sentence longer than `max` is cut at whitespace.
+        while end - start > max {
+            let cut = hard_cut(body, start, max);
+            push(span.start + start..span.start + cut);
+            start = cut;
+        }
+        last_end = end;
+    }
+    push(span.start + start..span.end);
+}
+
+/// The last whitespace (or char boundary) at most `max` bytes after `start`; past the next
+/// char when `max` is narrower than it, so every cut makes progress.
+fn hard_cut(body: &str, start: usize, max: usize) -> usize {
+    let mut limit = start + max;
+    while !body.is_char_boundary(limit) {
+        limit -= 1;
+    }
+    if limit == start {
+        return start + body[start..].chars().next().map_or(0, char::len_utf8);
+    }
+    body[start..limit]
+        .rfind(char::is_whitespace)
+        .filter(|&at| at > 0)
+        .map(|at| start + at)
+        .unwrap_or(limit)
+}
+
+fn trimmed(text: &str, range: Range<usize>) -> Range<usize> {
+    let slice = &text[range.clone()];
+    let start = range.start + (slice.len() - slice.trim_start().len());
+    start..start + slice.trim().len()
+}
+
+/// `text` as one context segment of at most `budget` bytes: its chunks headed by their
+/// structural paths, cut at a chunk boundary when the budget runs out. Also returns the
+/// number of sections left out.
+pub fn context_segment(text: &str, budget: usize) -> (String, usize) {
+    let chunks = chunk(text, MAX_CHUNK_BYTES.min(budget));
+    let mut segment = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let rendered = if chunk.path.is_empty() {
+            chunk.text.clone()
+        } else {
+            format!("[{}]\n{}", chunk.heading(), chunk.text)
+        };
+        if !segment.is_empty() && segment.len() + rendered.len() + 2 > budget {
+            let omitted = chunks.len() - index;
+            segment.push_str(&format!("\n\n[… {omitted} more sections omitted]"));
+            return (segment, omitted);
+        }
+        if !segment.is_empty() {
+            segment.push_str("\n\n");
+        }
+        segment.push_str(&rendered);
+    }
+    (segment, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(chunks: &[Chunk]) -> Vec<String> {
+        chunks.iter().map(Chunk::heading).collect()
+    }
+
+    #[test]
+    fn code_articles_split_into_commi_and_lettere() {
+        let code = "Art. 2043. Risarcimento per fatto illecito.\n\
+                    Qualunque fatto doloso o colposo, che cagiona ad altri un danno ingiusto, obbliga colui che ha commesso il fatto a risarcire il danno.\n\n\
+                    Art. 2050-bis (Responsabilità).\n\
+                    Chiunque cagiona danno ad altri nello svolgimento di un'attività pericolosa è tenuto al risarcimento.\n\n\
+                    Tale obbligo sussiste quando:\n\
+                    a) l'attività è pericolosa per sua natura;\n\
+                    b) i mezzi adoperati sono pericolosi.\n";
+
+        // Small articles stay whole.
+        let whole = chunk(code, 1000);
+        assert_eq!(paths(&whole), ["Art. 2043", "Art. 2050-bis"]);
+        assert!(whole[0].text.starts_with("Art. 2043.") && whole[0].text.ends_with("risarcire il danno."));
+        for c in &whole {
+            assert_eq!(&code[c.span.clone()], c.text);
+        }
+
+        // Larger ones fall back to commi, then lettere.
+        let fine = chunk(code, 60);
+        assert!(paths(&fine).contains(&"Art. 2043 › comma 1".to_string()));
+        assert!(paths(&fine).contains(&"Art. 2050-bis › comma 2 › lett. a)".to_string()));
+        assert!(fine.iter().any(|c| c.text == "b) i mezzi adoperati sono pericolosi."));
+    }
+
+    #[test]
+    fn decisions_split_into_sections_and_motivi() {
+        let decision = "Corte di Cassazione, Sez. III civile\n\n\
+                        MASSIMA\n\
+                        Il danno non patrimoniale è risarcibile nei soli casi previsti dalla legge.\n\n\
+                        FATTI DI CAUSA\n\
+                        1. Il ricorrente conveniva in giudizio la società.\n\n\
+                        RAGIONI DELLA DECISIONE\n\
+                        1. Con il primo motivo si deduce la violazione dell'art. 2059 c.c.\n\
+                        1.1. Il motivo è fondato.\n\
+                        2. Art. 2043 c.c. non è applicabile al caso.\n\n\
+                        P.Q.M.\n\
+                        La Corte accoglie il ricorso.\n";
+        let chunks = chunk(decision, 1000);
+        assert_eq!(
+            paths(&chunks),
+            [
+                "",
+                "Massima",
+                "Fatti di causa",
+                "Fatti di causa › punto 1",
+                "Ragioni della decisione",
+                "Ragioni della decisione › motivo 1",
+                "Ragioni della decisione › motivo 2",
+                "P.Q.M.",
+            ]
+        );
+        assert!(chunks[5].text.ends_with("Il motivo è fondato."), "1.1 stays inside motivo 1");
+    }
+
+    #[test]
+    fn contract_clauses_are_chunked_individually() {
+        let contract = "CONTRATTO DI LOCAZIONE\n\n\
+                        1. Oggetto. Il locatore concede in locazione l'immobile.\n\
+                        2. Canone. Il canone annuo è di euro 12.000.\n\
+                        2.1 Il canone è aggiornato annualmente.\n\
+                        Clausola 3 – Recesso\n\
+                        Il conduttore può recedere con preavviso di sei mesi.\n";
+        assert_eq!(
+            paths(&chunk(contract, 1000)),
+            ["", "clausola 1", "clausola 2", "Clausola 3"]
+        );
+        assert_eq!(
+            paths(&chunk(contract, 70)),
+            ["", "clausola 1", "clausola 2", "clausola 2 › clausola 2.1", "Clausola 3", "Clausola 3 › comma 1"]
+        );
+    }
+
+    #[test]
+    fn long_units_split_at_sentence_ends() {
+        let text = "Art. 1.\nPrima frase del comma; seconda frase del comma. Terza frase, ben più lunga delle altre due.";
+        let chunks = chunk(text, 40);
+        assert!(chunks.iter().all(|c| c.text.len() <= 40), "{chunks:#?}");
+        assert_eq!(chunks[1].text, "Prima frase del comma;");
+        assert!(chunks.iter().all(|c| c.heading().starts_with("Art. 1")));
+        for c in &chunks {
+            assert_eq!(&text[c.span.clone()], c.text);
+        }
+    }
+
+    #[test]
+    fn budgets_narrower_than_a_char_still_advance() {
+        for max in 1..=3 {
+            let chunks = chunk("éé", max);
+            assert_eq!(chunks.iter().map(|c| c.text.as_str()).collect::<String>(), "éé");
+        }
+        assert_eq!(chunk("€", 2)[0].text, "€");
+        assert!(!context_segment("éé", 1).0.is_empty());
+    }
+
+    #[test]
+    fn context_segments_stop_at_chunk_boundaries() {
+        let code = "Art. 1.\nPrimo articolo.\n\nArt. 2.\nSecondo articolo.\n\nArt. 3.\nTerzo articolo.\n";
+        let segment = context_segment(code, 50);
+        assert_eq!(segment, ("[Art. 1]\nArt. 1.\nPrimo articolo.\n\n[… 2 more sections omitted]".to_string(), 2));
+        assert_eq!(context_segment("Testo semplice.", 50), ("Testo semplice.".to_string(), 0));
+    }
+}
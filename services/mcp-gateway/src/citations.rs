@@ -0,0 +1,433 @@
+//! Italian legal citations: code and constitutional articles ("art. 1218 c.c.",
+//! "artt. 1453 e ss. cod. civ.", "art. 2 Cost."), legislation ("d.lgs. 196/2003",
+//! "L. 241/1990") and case law ("Cass. civ., Sez. III, n. 30574/2022", "Cons. Stato").
+//! Each reference is parsed into a typed [`Citation`] with a canonical spelling and the
+//! byte span where it was found.
+
+use std::{fmt, sync::LazyLock};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Codes (and the Constitution) articles can be cited from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Code {
+    CodiceCivile,
+    CodiceProceduraCivile,
+    CodicePenale,
+    CodiceProceduraPenale,
+    CodiceConsumo,
+    Costituzione,
+}
+
+impl Code {
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Code::CodiceCivile => "c.c.",
+            Code::CodiceProceduraCivile => "c.p.c.",
+            Code::CodicePenale => "c.p.",
+            Code::CodiceProceduraPenale => "c.p.p.",
+            Code::CodiceConsumo => "cod. cons.",
+            Code::Costituzione => "Cost.",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let key: String = text.to_lowercase().split_whitespace().collect();
+        Some(match key.as_str() {
+            "c.c." | "cod.civ." | "codicecivile" => Code::CodiceCivile,
+            "c.p.c." | "cod.proc.civ." | "codicediproceduracivile" => Code::CodiceProceduraCivile,
+            "c.p." | "cod.pen." | "codicepenale" => Code::CodicePenale,
+            "c.p.p." | "cod.proc.pen." | "codicediprocedurapenale" => Code::CodiceProceduraPenale,
+            "cod.cons." | "codicedelconsumo" => Code::CodiceConsumo,
+            "cost." | "costituzione" => Code::Costituzione,
+            _ => return None,
+        })
+    }
+}
+
+/// Kinds of primary legislation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Act {
+    Legge,
+    DecretoLegislativo,
+    DecretoLegge,
+    Dpr,
+}
+
+impl Act {
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Act::Legge => "L.",
+            Act::DecretoLegislativo => "d.lgs.",
+            Act::DecretoLegge => "d.l.",
+            Act::Dpr => "d.P.R.",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let key: String = text.to_lowercase().split_whitespace().collect();
+        Some(match key.trim_end_matches('.') {
+            "l" | "legge" => Act::Legge,
+            "d.lgs" | "dlgs" | "decretolegislativo" => Act::DecretoLegislativo,
+            "d.l" | "decreto-legge" | "decretolegge" => Act::DecretoLegge,
+            "d.p.r" | "dpr" => Act::Dpr,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Court {
+    Cassazione,
+    ConsiglioDiStato,
+    CorteCostituzionale,
+}
+
+impl Court {
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Court::Cassazione => "Cass.",
+            Court::ConsiglioDiStato => "Cons. Stato",
+            Court::CorteCostituzionale => "Corte cost.",
+        }
+    }
+}
+
+/// Civil or criminal division of the Cassazione.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Division {
+    Civile,
+    Penale,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Citation {
+    /// Articles of a code or of the Constitution
+    CodeArticle {
+        code: Code,
+        /// Article numbers as written in the code, e.g. `1453` or `2050-bis`
+        articles: Vec<String>,
+        #[serde(default)]
+        comma: Option<String>,
+        /// "e ss.": the article and the ones following it
+        #[serde(default)]
+        et_seq: bool,
+    },
+    /// An act, optionally narrowed to some of its articles
+    Legislation {
+        act: Act,
+        number: u32,
+        year: u16,
+        #[serde(default)]
+        articles: Vec<String>,
+    },
+    /// A decision; the court alone when no number is given
+    CaseLaw {
+        court: Court,
+        #[serde(default)]
+        division: Option<Division>,
+        /// Roman numeral, `Un.` (Sezioni Unite), `Lav.` or `Trib.`
+        #[serde(default)]
+        section: Option<String>,
+        #[serde(default)]
+        number: Option<u32>,
+        #[serde(default)]
+        year: Option<u16>,
+    },
+}
+
+fn articles_prefix(articles: &[String], et_seq: bool) -> String {
+    let head = if articles.len() > 1 { "artt." } else { "art." };
+    let list = match articles {
+        [] => String::new(),
+        [one] => one.clone(),
+        [init @ .., last] => format!("{} e {last}", init.join(", ")),
+    };
+    let ss = if et_seq { " ss." } else { "" };
+    format!("{head} {list}{ss}")
+}
+
+/// The canonical form: `art. 1218 c.c.`, `artt. 1453 ss. c.c.`, `art. 13 d.lgs. 196/2003`,
+/// `Cass. civ., Sez. III, n. 30574/2022`.
+impl fmt::Display for Citation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Citation::CodeArticle { code, articles, comma, et_seq } => {
+                write!(f, "{}", articles_prefix(articles, *et_seq))?;
+                if let Some(comma) = comma {
+                    write!(f, ", comma {comma},")?;
+                }
+                write!(f, " {}", code.abbreviation())
+            }
+            Citation::Legislation { act, number, year, articles } => {
+                if !articles.is_empty() {
+                    write!(f, "{} ", articles_prefix(articles, false))?;
+                }
+                write!(f, "{} {number}/{year}", act.abbreviation())
+            }
+            Citation::CaseLaw { court, division, section, number, year } => {
+                write!(f, "{}", court.abbreviation())?;
+                match division {
+                    Some(Division::Civile) => write!(f, " civ.")?,
+                    Some(Division::Penale) => write!(f, " pen.")?,
+                    None => {}
+                }
+                if let Some(section) = section {
+                    write!(f, ", Sez. {section}")?;
+                }
+                match (number, year) {
+                    (Some(number), Some(year)) => write!(f, ", n. {number}/{year}"),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// A citation found in a text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CitationMatch {
+    pub citation: Citation,
+    /// Canonical spelling, e.g. `art. 1453 ss. c.c.`
+    pub canonical: String,
+    /// The citation as written
+    pub text: String,
+    /// Byte offset of `text` in the source
+    pub start: usize,
+    /// Byte offset just past `text`
+    pub end: usize,
+}
+
+const SUFFIX: &str = r"(?:[\s-]?(?:bis|ter|quater|quinquies|sexies|septies|octies|novies|decies)\b)";
+const CODE: &str = r"c\.\s?p\.\s?c\.|c\.\s?p\.\s?p\.|c\.\s?p\.|c\.\s?c\.|cod\.\s?proc\.\s?civ\.|cod\.\s?proc\.\s?pen\.|cod\.\s?civ\.|cod\.\s?pen\.|cod\.\s?cons\.|codice\s+di\s+procedura\s+civile|codice\s+di\s+procedura\s+penale|codice\s+civile|codice\s+penale|codice\s+del\s+consumo|cost\.|costituzione";
+const ACT: &str = r"d\.?\s?lgs\.?|decreto\s+legislativo|d\.\s?l\.|decreto[-\s]legge|d\.\s?p\.\s?r\.|dpr|l\.|legge";
+
+static ACT_REF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(?P<act>{ACT})\s*(?:n\.\s*)?(?P<number>\d+)\s*(?:/|\s+del\s+)(?P<year>(?:18|19|20)\d{{2}})\b"
+    ))
+    .unwrap()
+});
+static ARTICLE_REF: LazyLock<Regex> = LazyLock::new(|| {
+    let number = format!(r"\d+{SUFFIX}?");
+    Regex::new(&format!(
+        r"(?i)\b(?:artt?\.|articol[oi]\b)\s*(?P<articles>{number}(?:\s*(?:,|\be\b)\s*{number})*)(?P<ss>\s*(?:e\s+)?(?:ss\.|segg\.|seguenti\b))?(?:\s*,?\s*(?:comma|co\.)\s*(?P<comma>\d+)\s*,?)?\s*,?\s*(?:(?:della|dello|del)\s+)?(?:(?P<code>{CODE})|(?P<act>{ACT})\s*(?:n\.\s*)?(?P<number>\d+)\s*(?:/|\s+del\s+)(?P<year>(?:18|19|20)\d{{2}})\b)"
+    ))
+    .unwrap()
+});
+static CASE_REF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?P<court>corte\s+di\s+cassazione|cassazione|cass\.|consiglio\s+di\s+stato|cons\.\s*(?:di\s+)?st(?:ato\b|\.)|corte\s+costituzionale|corte\s+cost\.|c\.\s?cost\.)(?:\s*,?\s*(?P<division>civ\.|civile\b|pen\.|penale\b))?(?:\s*,?\s*(?:sez\.|sezioni\b|sezione\b)\s*(?P<section>unite\b|un\.|lav\.|lavoro\b|trib\.|[ivx]+\b|\d\b)(?:\s*(?P<division2>civ\.|civile\b|pen\.|penale\b))?|\s*,?\s*(?P<united>s\.\s?u\.|ss\.\s?uu\.))?(?:\s*,?\s*(?:(?:sentenza|sent\.|ordinanza|ord\.)\s*)?(?:n\.\s*)?(?P<number>\d+)\s*(?:/|\s+del\s+)(?P<year>(?:19|20)\d{2})\b)?",
+    )
+    .unwrap()
+});
+static ARTICLE_SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s*(?:,|\be\b)\s*").unwrap());
+
+/// Every citation in `text`, in order of appearance. Where references overlap ("art. 13
+/// d.lgs. 196/2003" contains "d.lgs. 196/2003") the longest one wins.
+pub fn extract(text: &str) -> Vec<CitationMatch> {
+    let mut found: Vec<(usize, usize, Citation)> = Vec::new();
+    for caps in ARTICLE_REF.captures_iter(text) {
+        found.extend(article(&caps));
+    }
+    for caps in ACT_REF.captures_iter(text) {
+        found.extend(legislation(&caps, Vec::new()));
+    }
+    for caps in CASE_REF.captures_iter(text) {
+        found.extend(case_law(&caps));
+    }
+    found.sort_by_key(|(start, end, _)| (*start, std::cmp::Reverse(*end)));
+
+    let mut matches: Vec<CitationMatch> = Vec::new();
+    for (start, end, citation) in found {
+        if matches.last().is_some_and(|last| start < last.end) {
+            continue;
+        }
+        matches.push(CitationMatch {
+            canonical: citation.to_string(),
+            citation,
+            text: text[start..end].to_string(),
+            start,
+            end,
+        });
+    }
+    matches
+}
+
+fn article_number(raw: &str) -> String {
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (number, suffix) = raw.split_at(split);
+    let suffix = suffix.trim_start_matches([' ', '-', '\t', '\n']).to_lowercase();
+    if suffix.is_empty() {
+        number.to_string()
+    } else {
+        format!("{number}-{suffix}")
+    }
+}
+
+fn article(caps: &Captures) -> Option<(usize, usize, Citation)> {
+    let whole = caps.get(0)?;
+    let articles: Vec<String> = ARTICLE_SEPARATOR
+        .split(&caps["articles"])
+        .filter(|a| !a.is_empty())
+        .map(article_number)
+        .collect();
+    if let Some(code) = caps.name("code") {
+        let citation = Citation::CodeArticle {
+            code: Code::parse(code.as_str())?,
+            articles,
+            comma: caps.name("comma").map(|c| c.as_str().to_string()),
+            et_seq: caps.name("ss").is_some(),
+        };
+        return Some((whole.start(), whole.end(), citation));
+    }
+    let (_, _, act) = legislation(caps, articles)?;
+    Some((whole.start(), whole.end(), act))
+}
+
+fn legislation(caps: &Captures, articles: Vec<String>) -> Option<(usize, usize, Citation)> {
+    let whole = caps.get(0)?;
+    let citation = Citation::Legislation {
+        act: Act::parse(&caps["act"])?,
+        number: caps["number"].parse().ok()?,
+        year: caps["year"].parse().ok()?,
+        articles,
+    };
+    Some((whole.start(), whole.end(), citation))
+}
+
+fn case_law(caps: &Captures) -> Option<(usize, usize, Citation)> {
+    let whole = caps.get(0)?;
+    let court = caps["court"].to_lowercase();
+    let court = if court.contains("cass") {
+        Court::Cassazione
+    } else if court.contains("st") && !court.contains("cost") {
+        Court::ConsiglioDiStato
+    } else {
+        Court::CorteCostituzionale
+    };
+
+    let division = caps.name("division").or(caps.name("division2")).map(|d| {
+        if d.as_str().to_lowercase().starts_with("civ") {
+            Division::Civile
+        } else {
+            Division::Penale
+        }
+    });
+    let section = match (caps.name("section"), caps.name("united")) {
+        (_, Some(_)) => Some("Un.".to_string()),
+        (Some(section), None) => Some(section_name(section.as_str())),
+        (None, None) => None,
+    };
+    let number = caps.name("number").and_then(|n| n.as_str().parse().ok());
+    // A court named in full without details is prose ("ricorso per cassazione"); the
+    // abbreviated forms are only used to cite.
+    if number.is_none() && section.is_none() && division.is_none() && !caps["court"].contains('.') {
+        return None;
+    }
+    let citation = Citation::CaseLaw {
+        court,
+        // The labour section only sits in civil matters.
+        division: division.or((section.as_deref() == Some("Lav.")).then_some(Division::Civile)),
+        section,
+        number,
+        year: caps.name("year").and_then(|y| y.as_str().parse().ok()),
+    };
+    Some((whole.start(), whole.end(), citation))
+}
+
+fn section_name(raw: &str) -> String {
+    const ROMAN: [&str; 9] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX"];
+    let raw = raw.to_lowercase();
+    match raw.as_str() {
+        "unite" | "un." => "Un.".to_string(),
+        "lav." | "lavoro" => "Lav.".to_string(),
+        "trib." => "Trib.".to_string(),
+        digit if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => {
+            let n = (digit.as_bytes()[0] - b'0') as usize;
+            ROMAN.get(n.wrapping_sub(1)).map_or(digit.to_string(), |r| r.to_string())
+        }
+        roman => roman.to_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(text: &str) -> Vec<String> {
+        extract(text).into_iter().map(|m| m.canonical).collect()
+    }
+
+    #[test]
+    fn parses_code_articles() {
+        assert_eq!(canonical("ai sensi dell'art. 1218 c.c."), ["art. 1218 c.c."]);
+        assert_eq!(canonical("artt. 1453 e ss. cod. civ."), ["art. 1453 ss. c.c."]);
+        assert_eq!(canonical("art. 2 Cost."), ["art. 2 Cost."]);
+        assert_eq!(canonical("artt. 1453, 1454 e 1455 codice civile"), ["artt. 1453, 1454 e 1455 c.c."]);
+        assert_eq!(canonical("art. 360, comma 1, c.p.c."), ["art. 360, comma 1, c.p.c."]);
+        assert_eq!(canonical("art. 2050 bis c.p."), ["art. 2050-bis c.p."]);
+        assert_eq!(
+            extract("art. 3 della Costituzione")[0].citation,
+            Citation::CodeArticle {
+                code: Code::Costituzione,
+                articles: vec!["3".into()],
+                comma: None,
+                et_seq: false
+            }
+        );
+        // An article without a source is not resolvable, and neither is prose.
+        assert!(extract("l'art. 5 prevede un costo. Il dl è pronto").is_empty());
+    }
+
+    #[test]
+    fn parses_legislation() {
+        assert_eq!(canonical("il d.lgs. 196/2003 e la L. 241/1990"), ["d.lgs. 196/2003", "L. 241/1990"]);
+        assert_eq!(canonical("D.Lgs. n. 81 del 2008"), ["d.lgs. 81/2008"]);
+        assert_eq!(canonical("art. 13 d.lgs. 196/2003"), ["art. 13 d.lgs. 196/2003"]);
+        assert_eq!(canonical("legge n. 392/1978"), ["L. 392/1978"]);
+    }
+
+    #[test]
+    fn parses_case_law() {
+        let text = "Cass. civ., Sez. III, n. 30574/2022";
+        assert_eq!(
+            extract(text)[0].citation,
+            Citation::CaseLaw {
+                court: Court::Cassazione,
+                division: Some(Division::Civile),
+                section: Some("III".into()),
+                number: Some(30574),
+                year: Some(2022),
+            }
+        );
+        assert_eq!(canonical(text), [text]);
+        assert_eq!(canonical("Cass. Civ. 30574/2022"), ["Cass. civ., n. 30574/2022"]);
+        assert_eq!(canonical("Cass., S.U., n. 26972 del 2008"), ["Cass., Sez. Un., n. 26972/2008"]);
+        assert_eq!(canonical("Cass. sez. lav. n. 123/2021"), ["Cass. civ., Sez. Lav., n. 123/2021"]);
+        assert_eq!(canonical("secondo Cons. Stato, sez. 6, n. 1234/2021"), ["Cons. Stato, Sez. VI, n. 1234/2021"]);
+        assert_eq!(canonical("come ritenuto dal Cons. Stato"), ["Cons. Stato"]);
+        assert!(extract("il ricorso per cassazione è inammissibile").is_empty());
+        assert_eq!(canonical("Corte di Cassazione, Sezioni Unite, n. 500/1999"), ["Cass., Sez. Un., n. 500/1999"]);
+        assert_eq!(canonical("Corte cost., sentenza n. 242/2019"), ["Corte cost., n. 242/2019"]);
+    }
+
+    #[test]
+    fn records_byte_spans() {
+        let text = "Responsabilità ex art. 1218 c.c. (Cass. civ., Sez. III, n. 30574/2022).";
+        let found = extract(text);
+        assert_eq!(found.len(), 2);
+        for m in &found {
+            assert_eq!(&text[m.start..m.end], m.text);
+        }
+        assert_eq!(found[0].text, "art. 1218 c.c.");
+        assert_eq!(found[1].text, "Cass. civ., Sez. III, n. 30574/2022");
+    }
+}
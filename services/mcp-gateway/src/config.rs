@@ -1,56 +1,296 @@
+//! Gateway configuration, layered as built-in defaults < TOML file < environment variables.
+//! Parsing is strict: a malformed or unknown setting is an error naming the key and where
+//! it came from. Keys in [`RELOADABLE`] are re-read from the file while the gateway runs;
+//! everything else needs a restart.
+
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+use crate::{
+    auth::DEFAULT_PRINCIPAL,
+    chunking,
+    content_policy::{self, InjectionAction},
+    mcp_client::ServerSpec,
+    rate_limit,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub bind_addr: String,
     pub api_key: String,
+    /// Further keys, `<principal>=<key>`; rate limits are kept per principal
+    pub api_keys: Vec<String>,
     pub openai_api_key: Option<String>,
     pub gpt_model: String,
     pub vector_db_url: String,
+    /// Qdrant collection holding the legal knowledge base
+    pub kb_collection: String,
     pub storage_path: String,
     pub encryption_key: String,
     pub log_level: String,
     pub local_model_path: Option<String>,
     pub rate_limit_per_second: u32,
     pub rate_limit_burst_size: u32,
+    /// Online reasoning calls per principal per minute
+    pub rate_limit_online_per_minute: u32,
+    /// Local reasoning calls per principal per minute
+    pub rate_limit_local_per_minute: u32,
+    /// Weight of a request per route, `<route>=<cost>`; unlisted routes cost 1
+    pub rate_limit_costs: Vec<String>,
     pub max_request_size: usize,
+    /// Largest accepted document upload, in bytes
+    pub max_document_size: usize,
+    /// Largest matter workspace file a query may reference, in bytes
+    pub max_workspace_file_size: u64,
+    /// Largest total of the workspace files referenced by one query, in bytes
+    pub max_workspace_total_size: u64,
+    /// Context budget for each document or file referenced by a query, in bytes; longer
+    /// texts are cut at a section boundary and reported in `truncated`
+    pub context_document_bytes: usize,
     pub enable_compression: bool,
     pub enable_cors: bool,
+    /// Serve `/metrics` without the API key, for scrapers that cannot send one
+    pub metrics_public: bool,
+    /// Allowed CORS origins; `*` allows any.
+    pub cors_origins: Vec<String>,
+    /// Server URLs advertised in the OpenAPI document; defaults to localhost on the bind port.
+    pub openapi_servers: Vec<String>,
+    pub openai_base_url: String,
+    pub ledger_path: String,
+    pub log_dir: String,
+    /// Directory of `trusted_models.json`, its `.asc` signature and `publickey.asc`; defaults
+    /// to the local model's directory. An unsigned or edited registry is not trusted
+    pub registry_dir: Option<String>,
+    /// Ed25519 seed signing reasoning responses; defaults to `<storage_path>/keys/response_signing.key`
+    pub signing_key_path: Option<String>,
+    pub health_cache_ttl_secs: u64,
+    pub health_probe_timeout_secs: u64,
+    pub min_free_disk_mb: u64,
+    pub readiness_checks: Vec<String>,
+    pub startup_checks: Vec<String>,
+    /// Local MCP tool servers offered to the online reasoner:
+    /// `name=<command line>` (stdio) or `name=http(s)://…` (streamable HTTP)
+    pub mcp_servers: Vec<String>,
+    /// What happens to context segments matching an injection rule: `fence` or `quarantine`
+    pub injection_action: String,
+    /// Extra case-insensitive regexes flagging instruction injection in context
+    pub injection_patterns: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_addr: "0.0.0.0:5142".to_string(),
+            api_key: "counsel-ai-dev-key".to_string(),
+            api_keys: Vec::new(),
+            openai_api_key: None,
+            gpt_model: "gpt-4".to_string(),
+            vector_db_url: "http://localhost:6333".to_string(),
+            kb_collection: "legal_kb".to_string(),
+            storage_path: "/data/encrypted".to_string(),
+            encryption_key: "default-encryption-key-change-in-production".to_string(),
+            log_level: "info".to_string(),
+            local_model_path: None,
+            rate_limit_per_second: 10,
+            rate_limit_burst_size: 20,
+            rate_limit_online_per_minute: 30,
+            rate_limit_local_per_minute: 4,
+            rate_limit_costs: vec![
+                "/v1/query=2".into(),
+                "/v1/reason=5".into(),
+                "/v1/reason_local=10".into(),
+                "/v1/verify=5".into(),
+                "/v1/documents=10".into(),
+                "/mcp=2".into(),
+            ],
+            max_request_size: 1048576, // 1MB
+            max_document_size: 26214400, // 25MB
+            max_workspace_file_size: 5242880, // 5MB
+            max_workspace_total_size: 20971520, // 20MB
+            context_document_bytes: chunking::DOCUMENT_CONTEXT_BYTES,
+            enable_compression: true,
+            enable_cors: true,
+            metrics_public: false,
+            cors_origins: vec!["*".to_string()],
+            openapi_servers: Vec::new(),
+            openai_base_url: "https://api.openai.com/v1".to_string(),
+            ledger_path: "/logs/ledger.jsonl".to_string(),
+            log_dir: "/logs".to_string(),
+            registry_dir: None,
+            signing_key_path: None,
+            health_cache_ttl_secs: 15,
+            health_probe_timeout_secs: 3,
+            min_free_disk_mb: 512,
+            readiness_checks: vec!["local_model".into(), "storage".into(), "ledger".into()],
+            startup_checks: vec!["storage".into(), "ledger".into()],
+            mcp_servers: Vec::new(),
+            injection_action: "fence".to_string(),
+            injection_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Every config key with the environment variable that overrides it.
+const ENV_VARS: &[(&str, &str)] = &[
+    ("bind_addr", "BIND_ADDR"),
+    ("api_key", "API_KEY"),
+    ("api_keys", "API_KEYS"),
+    ("openai_api_key", "OPENAI_API_KEY"),
+    ("gpt_model", "GPT_MODEL"),
+    ("vector_db_url", "VECTOR_DB_URL"),
+    ("kb_collection", "KB_COLLECTION"),
+    ("storage_path", "STORAGE_PATH"),
+    ("encryption_key", "ENCRYPTION_KEY"),
+    ("log_level", "LOG_LEVEL"),
+    ("local_model_path", "LOCAL_MODEL_PATH"),
+    ("rate_limit_per_second", "RATE_LIMIT_PER_SECOND"),
+    ("rate_limit_burst_size", "RATE_LIMIT_BURST_SIZE"),
+    ("rate_limit_online_per_minute", "RATE_LIMIT_ONLINE_PER_MINUTE"),
+    ("rate_limit_local_per_minute", "RATE_LIMIT_LOCAL_PER_MINUTE"),
+    ("rate_limit_costs", "RATE_LIMIT_COSTS"),
+    ("max_request_size", "MAX_REQUEST_SIZE"),
+    ("max_document_size", "MAX_DOCUMENT_SIZE"),
+    ("max_workspace_file_size", "MAX_WORKSPACE_FILE_SIZE"),
+    ("max_workspace_total_size", "MAX_WORKSPACE_TOTAL_SIZE"),
+    ("context_document_bytes", "CONTEXT_DOCUMENT_BYTES"),
+    ("enable_compression", "ENABLE_COMPRESSION"),
+    ("enable_cors", "ENABLE_CORS"),
+    ("metrics_public", "METRICS_PUBLIC"),
+    ("cors_origins", "CORS_ORIGINS"),
+    ("openapi_servers", "OPENAPI_SERVERS"),
+    ("openai_base_url", "OPENAI_BASE_URL"),
+    ("ledger_path", "LEDGER_PATH"),
+    ("log_dir", "LOG_DIR"),
+    ("registry_dir", "COUNSEL_REGISTRY_DIR"),
+    ("signing_key_path", "SIGNING_KEY_PATH"),
+    ("health_cache_ttl_secs", "HEALTH_CACHE_TTL_SECS"),
+    ("health_probe_timeout_secs", "HEALTH_PROBE_TIMEOUT_SECS"),
+    ("min_free_disk_mb", "MIN_FREE_DISK_MB"),
+    ("readiness_checks", "READINESS_CHECKS"),
+    ("startup_checks", "STARTUP_CHECKS"),
+    ("mcp_servers", "MCP_SERVERS"),
+    ("injection_action", "INJECTION_ACTION"),
+    ("injection_patterns", "INJECTION_PATTERNS"),
+];
+
+/// Keys applied to the running gateway when the config file changes.
+pub const RELOADABLE: &[&str] = &[
+    "rate_limit_per_second",
+    "rate_limit_burst_size",
+    "rate_limit_online_per_minute",
+    "rate_limit_local_per_minute",
+    "rate_limit_costs",
+    "context_document_bytes",
+    "log_level",
+    "enable_cors",
+    "cors_origins",
+    "local_model_path",
+];
+
+/// Probe names accepted in `READINESS_CHECKS` and `STARTUP_CHECKS`.
+pub const PROBE_NAMES: &[&str] = &["vector_db", "online_reasoner", "local_model", "ledger", "storage"];
+
+const USAGE: &str = "usage: mcp-gateway [check-config | mcp-stdio] [--config <file>]";
+
+/// Command line: `mcp-gateway [check-config | mcp-stdio] [--config <file>]`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Cli {
+    /// Validate the configuration, print it and exit.
+    pub check_config: bool,
+    /// Serve MCP over stdin/stdout instead of HTTP.
+    pub mcp_stdio: bool,
+    /// TOML config file; `--config` wins over `COUNSEL_CONFIG`.
+    pub file: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "check-config" => cli.check_config = true,
+                "mcp-stdio" => cli.mcp_stdio = true,
+                "--config" => cli.file = Some(args.next().context(USAGE)?.into()),
+                other => bail!("unknown argument `{other}` ({USAGE})"),
+            }
+        }
+        if cli.check_config && cli.mcp_stdio {
+            bail!("`check-config` and `mcp-stdio` are exclusive ({USAGE})");
+        }
+        if cli.file.is_none() {
+            cli.file = env::var_os("COUNSEL_CONFIG").filter(|v| !v.is_empty()).map(PathBuf::from);
+        }
+        Ok(cli)
+    }
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Config {
-            bind_addr: env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:5142".to_string()),
-            api_key: env::var("API_KEY").unwrap_or_else(|_| "counsel-ai-dev-key".to_string()),
-            openai_api_key: env::var("OPENAI_API_KEY").ok(),
-            gpt_model: env::var("GPT_MODEL").unwrap_or_else(|_| "gpt-4".to_string()),
-            vector_db_url: env::var("VECTOR_DB_URL").unwrap_or_else(|_| "http://localhost:6333".to_string()),
-            storage_path: env::var("STORAGE_PATH").unwrap_or_else(|_| "/data/encrypted".to_string()),
-            encryption_key: env::var("ENCRYPTION_KEY").unwrap_or_else(|_| "default-encryption-key-change-in-production".to_string()),
-            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
-            local_model_path: env::var("LOCAL_MODEL_PATH").ok(),
-            rate_limit_per_second: env::var("RATE_LIMIT_PER_SECOND")
-                .unwrap_or_else(|_| "10".to_string())
-                .parse()
-                .unwrap_or(10),
-            rate_limit_burst_size: env::var("RATE_LIMIT_BURST_SIZE")
-                .unwrap_or_else(|_| "20".to_string())
-                .parse()
-                .unwrap_or(20),
-            max_request_size: env::var("MAX_REQUEST_SIZE")
-                .unwrap_or_else(|_| "1048576".to_string()) // 1MB
-                .parse()
-                .unwrap_or(1048576),
-            enable_compression: env::var("ENABLE_COMPRESSION")
-                .unwrap_or_else(|_| "true".to_string())
-                .parse()
-                .unwrap_or(true),
-            enable_cors: env::var("ENABLE_CORS")
-                .unwrap_or_else(|_| "true".to_string())
-                .parse()
-                .unwrap_or(true),
+    pub fn signing_key_path(&self) -> PathBuf {
+        match &self.signing_key_path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.storage_path).join("keys").join("response_signing.key"),
+        }
+    }
+
+    /// Defaults, overlaid with `file` (if any) and then the process environment.
+    /// Empty environment variables count as unset.
+    pub fn load(file: Option<&Path>) -> Result<Self> {
+        Self::layered(file, |var| env::var(var).ok())
+    }
+
+    /// [`Config::load`] followed by [`Config::validate`].
+    pub fn load_validated(file: Option<&Path>) -> Result<Self> {
+        let config = Self::load(file)?;
+        if let Err(errors) = config.validate() {
+            bail!("invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    fn layered(file: Option<&Path>, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let defaults = toml::Table::try_from(Config::default())?;
+        let mut merged = defaults.clone();
+        // Where each non-default key came from, for error messages.
+        let mut origin: HashMap<String, String> = HashMap::new();
+
+        if let Some(path) = file {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("failed to read config file {}", path.display()))?;
+            let table: toml::Table = text.parse().map_err(|e| anyhow!("{}: {e}", path.display()))?;
+            for (key, value) in table {
+                if !ENV_VARS.iter().any(|(k, _)| *k == key) {
+                    bail!("{}: unknown key `{key}`", path.display());
+                }
+                origin.insert(key.clone(), path.display().to_string());
+                merged.insert(key, value);
+            }
+        }
+
+        for (key, var) in ENV_VARS {
+            let Some(raw) = env(var).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let value = env_value(&raw, defaults.get(*key))
+                .with_context(|| format!("{var}: invalid value {raw:?} for `{key}`"))?;
+            merged.insert(key.to_string(), value);
+            origin.insert(key.to_string(), format!("environment variable {var}"));
+        }
+
+        serde_path_to_error::deserialize(toml::Value::Table(merged)).map_err(|e| {
+            let path = e.path().to_string();
+            let key = path.split(['.', '[']).next().unwrap_or_default();
+            let from = origin.get(key).map(String::as_str).unwrap_or("defaults");
+            anyhow!("invalid `{path}` (from {from}): {}", e.inner().message())
         })
     }
 
@@ -73,14 +313,306 @@ impl Config {
             errors.push("RATE_LIMIT_BURST_SIZE must be greater than 0".to_string());
         }
 
+        for (var, value) in [
+            ("RATE_LIMIT_ONLINE_PER_MINUTE", self.rate_limit_online_per_minute),
+            ("RATE_LIMIT_LOCAL_PER_MINUTE", self.rate_limit_local_per_minute),
+        ] {
+            if value == 0 {
+                errors.push(format!("{var} must be greater than 0"));
+            }
+        }
+
+        for entry in &self.rate_limit_costs {
+            match rate_limit::parse_cost(entry) {
+                Ok((route, cost)) if cost > self.rate_limit_burst_size => errors.push(format!(
+                    "RATE_LIMIT_COSTS charges {route} {cost}, more than RATE_LIMIT_BURST_SIZE"
+                )),
+                Ok(_) => {}
+                Err(e) => errors.push(format!("RATE_LIMIT_COSTS entry '{entry}' is invalid: {e}")),
+            }
+        }
+
+        let mut principals = vec![DEFAULT_PRINCIPAL];
+        for entry in &self.api_keys {
+            match entry.split_once('=') {
+                Some((name, _)) if principals.contains(&name) => {
+                    errors.push(format!("API_KEYS names principal '{name}' more than once or uses a reserved name"));
+                }
+                Some((name, key)) if !name.is_empty() && key.len() >= 16 && key != self.api_key => principals.push(name),
+                _ => errors.push(format!(
+                    "API_KEYS entries must be <principal>=<key> with a distinct key of at least 16 characters (got '{}')",
+                    entry.split_once('=').map_or("<no principal>", |(name, _)| name)
+                )),
+            }
+        }
+
         if self.max_request_size == 0 {
             errors.push("MAX_REQUEST_SIZE must be greater than 0".to_string());
         }
 
+        if self.max_document_size == 0 {
+            errors.push("MAX_DOCUMENT_SIZE must be greater than 0".to_string());
+        }
+
+        if self.max_workspace_file_size == 0 || self.max_workspace_total_size < self.max_workspace_file_size {
+            errors.push("MAX_WORKSPACE_FILE_SIZE must be greater than 0 and at most MAX_WORKSPACE_TOTAL_SIZE".to_string());
+        }
+
+        if self.context_document_bytes == 0 {
+            errors.push("CONTEXT_DOCUMENT_BYTES must be greater than 0".to_string());
+        }
+
+        if self.health_probe_timeout_secs == 0 {
+            errors.push("HEALTH_PROBE_TIMEOUT_SECS must be greater than 0".to_string());
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log_level) {
+            errors.push(format!("LOG_LEVEL is not a valid filter: {e}"));
+        }
+
+        if self.bind_addr.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(format!("BIND_ADDR '{}' is not a socket address", self.bind_addr));
+        }
+
+        for (var, checks) in [("READINESS_CHECKS", &self.readiness_checks), ("STARTUP_CHECKS", &self.startup_checks)] {
+            for name in checks.iter().filter(|n| !PROBE_NAMES.contains(&n.as_str())) {
+                errors.push(format!("{var} contains unknown probe '{name}' (expected one of {})", PROBE_NAMES.join(", ")));
+            }
+        }
+
+        let mut server_names = Vec::new();
+        for entry in &self.mcp_servers {
+            match ServerSpec::parse(entry) {
+                Ok(spec) if server_names.contains(&spec.name) => {
+                    errors.push(format!("MCP_SERVERS names '{}' more than once", spec.name));
+                }
+                Ok(spec) => server_names.push(spec.name),
+                Err(e) => errors.push(format!("MCP_SERVERS entry '{entry}' is invalid: {e}")),
+            }
+        }
+
+        if let Err(e) = InjectionAction::parse(&self.injection_action) {
+            errors.push(format!("INJECTION_ACTION is invalid: {e}"));
+        }
+        for pattern in &self.injection_patterns {
+            if let Err(e) = content_policy::validate_pattern(pattern) {
+                errors.push(format!("INJECTION_PATTERNS entry '{pattern}' is not a valid regex: {e}"));
+            }
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
             Err(errors)
         }
     }
-}
\ No newline at end of file
+
+    /// The effective configuration as TOML, with secrets masked.
+    pub fn to_masked_toml(&self) -> Result<String> {
+        let mut masked = self.clone();
+        masked.api_key = "***".to_string();
+        masked.encryption_key = "***".to_string();
+        for entry in &mut masked.api_keys {
+            let name = entry.split_once('=').map_or("", |(name, _)| name);
+            *entry = format!("{name}=***");
+        }
+        if masked.openai_api_key.is_some() {
+            masked.openai_api_key = Some("***".to_string());
+        }
+        Ok(toml::to_string(&masked)?)
+    }
+}
+
+/// Convert an environment value to the TOML type of the key's default; lists are comma separated.
+fn env_value(raw: &str, default: Option<&toml::Value>) -> Result<toml::Value> {
+    Ok(match default {
+        Some(toml::Value::Integer(_)) => toml::Value::Integer(raw.trim().parse().context("expected an integer")?),
+        Some(toml::Value::Boolean(_)) => toml::Value::Boolean(raw.trim().parse().context("expected true or false")?),
+        Some(toml::Value::Array(_)) => toml::Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| toml::Value::String(s.to_string()))
+                .collect(),
+        ),
+        _ => toml::Value::String(raw.to_string()),
+    })
+}
+
+/// Keys that differed in a reload.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reload {
+    pub applied: Vec<&'static str>,
+    pub restart_required: Vec<&'static str>,
+}
+
+/// The running configuration. Reloadable settings are swapped in place; readers
+/// call [`LiveConfig::get`] per use, or [`LiveConfig::subscribe`] to react to changes.
+#[derive(Clone)]
+pub struct LiveConfig(Arc<watch::Sender<Arc<Config>>>);
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(watch::channel(Arc::new(config)).0))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.0.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.0.subscribe()
+    }
+
+    /// Apply the [`RELOADABLE`] settings of `next` and report which keys changed.
+    pub fn reload(&self, next: &Config) -> Result<Reload> {
+        let current = toml::Table::try_from(&*self.get())?;
+        let next = toml::Table::try_from(next)?;
+        let mut merged = current.clone();
+        let mut reload = Reload::default();
+
+        for (key, _) in ENV_VARS {
+            if current.get(*key) == next.get(*key) {
+                continue;
+            }
+            if !RELOADABLE.contains(key) {
+                reload.restart_required.push(*key);
+                continue;
+            }
+            match next.get(*key) {
+                Some(value) => merged.insert(key.to_string(), value.clone()),
+                None => merged.remove(*key),
+            };
+            reload.applied.push(*key);
+        }
+
+        if !reload.applied.is_empty() {
+            let config: Config = toml::Value::Table(merged).try_into()?;
+            self.0.send_replace(Arc::new(config));
+        }
+        Ok(reload)
+    }
+}
+
+/// Poll `path` for changes and apply reloadable settings. An invalid file is
+/// reported and ignored, leaving the running configuration untouched.
+pub fn watch_file(path: PathBuf, live: LiveConfig, interval: Duration) -> tokio::task::JoinHandle<()> {
+    let modified = |path: &Path| -> Option<SystemTime> { fs::metadata(path).and_then(|m| m.modified()).ok() };
+    tokio::spawn(async move {
+        let mut last = modified(&path);
+        loop {
+            tokio::time::sleep(interval).await;
+            let now = modified(&path);
+            if now == last {
+                continue;
+            }
+            last = now;
+
+            let reload = Config::load_validated(Some(&path)).and_then(|next| live.reload(&next));
+            match reload {
+                Ok(reload) => {
+                    if !reload.applied.is_empty() {
+                        tracing::info!("Reloaded {} from {}", reload.applied.join(", "), path.display());
+                    }
+                    for key in reload.restart_required {
+                        tracing::warn!("`{key}` changed in {}; restart the gateway to apply it", path.display());
+                    }
+                }
+                Err(e) => tracing::warn!("Ignoring invalid configuration in {}: {e:#}", path.display()),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, contents: &str) -> PathBuf {
+        let path = dir.path().join("gateway.toml");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn env(vars: &[(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        move |var| vars.get(var).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn environment_overrides_file_overrides_defaults() {
+        let dir = TempDir::new().unwrap();
+        let file = write(
+            &dir,
+            "rate_limit_per_second = 5\nrate_limit_burst_size = 7\ncors_origins = [\"https://a.example\"]\n",
+        );
+        let config = Config::layered(
+            Some(&file),
+            env(&[("RATE_LIMIT_PER_SECOND", "9"), ("OPENAI_API_KEY", ""), ("STARTUP_CHECKS", "ledger, storage")]),
+        )
+        .unwrap();
+
+        assert_eq!(config.rate_limit_per_second, 9);
+        assert_eq!(config.rate_limit_burst_size, 7);
+        assert_eq!(config.cors_origins, ["https://a.example"]);
+        assert_eq!(config.openai_api_key, None, "empty variables count as unset");
+        assert_eq!(config.startup_checks, ["ledger", "storage"]);
+        assert_eq!(config.max_request_size, Config::default().max_request_size);
+    }
+
+    #[test]
+    fn errors_name_the_offending_key_and_source() {
+        let dir = TempDir::new().unwrap();
+
+        let err = Config::layered(None, env(&[("RATE_LIMIT_PER_SECOND", "ten")])).unwrap_err();
+        assert!(format!("{err:#}").contains("RATE_LIMIT_PER_SECOND"), "{err:#}");
+
+        let err = Config::layered(None, env(&[("RATE_LIMIT_BURST_SIZE", "-1")])).unwrap_err();
+        assert!(err.to_string().contains("`rate_limit_burst_size` (from environment variable RATE_LIMIT_BURST_SIZE)"), "{err}");
+
+        let file = write(&dir, "enable_cors = \"yes\"\n");
+        let err = Config::layered(Some(&file), env(&[])).unwrap_err();
+        assert!(err.to_string().contains("`enable_cors`"), "{err}");
+        assert!(err.to_string().contains("gateway.toml"), "{err}");
+
+        let file = write(&dir, "rate_limit = 3\n");
+        let err = Config::layered(Some(&file), env(&[])).unwrap_err();
+        assert!(err.to_string().contains("unknown key `rate_limit`"), "{err}");
+    }
+
+    #[test]
+    fn reload_applies_only_safe_settings() {
+        let live = LiveConfig::new(Config::default());
+        let rx = live.subscribe();
+        let next = Config {
+            rate_limit_per_second: 50,
+            log_level: "debug".to_string(),
+            local_model_path: Some("/models/phi-3.gguf".to_string()),
+            bind_addr: "127.0.0.1:9000".to_string(),
+            ..Config::default()
+        };
+
+        let reload = live.reload(&next).unwrap();
+        assert_eq!(reload.applied, ["log_level", "local_model_path", "rate_limit_per_second"]);
+        assert_eq!(reload.restart_required, ["bind_addr"]);
+        assert!(rx.has_changed().unwrap());
+
+        let current = live.get();
+        assert_eq!(current.rate_limit_per_second, 50);
+        assert_eq!(current.local_model_path.as_deref(), Some("/models/phi-3.gguf"));
+        assert_eq!(current.bind_addr, Config::default().bind_addr);
+
+        assert_eq!(live.reload(&live.get()).unwrap(), Reload::default());
+    }
+
+    #[test]
+    fn cli_accepts_check_config_and_file() {
+        let cli = Cli::parse(["check-config", "--config", "/etc/gateway.toml"].map(String::from)).unwrap();
+        assert!(cli.check_config);
+        assert_eq!(cli.file.as_deref(), Some(Path::new("/etc/gateway.toml")));
+        assert!(Cli::parse(["--verbose".to_string()]).is_err());
+        assert!(Cli::parse(["mcp-stdio".to_string()]).unwrap().mcp_stdio);
+        assert!(Cli::parse(["check-config", "mcp-stdio"].map(String::from)).is_err());
+    }
+}
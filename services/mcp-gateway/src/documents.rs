@@ -0,0 +1,292 @@
+//! Uploaded case documents. Text is extracted on upload (PDF, DOCX or plain text); the
+//! original and its metadata are kept encrypted with AES-256-GCM under
+//! `<storage_path>/documents`, and queries refer to documents by id.
+//!
+//! Each file is `nonce (12 bytes) || ciphertext || tag`, keyed by SHA-256 of
+//! `encryption_key`. Nothing about a document is stored in the clear.
+
+use std::{
+    io::{Cursor, Read},
+    path::PathBuf,
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use quick_xml::events::Event;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+const NONCE_LEN: usize = 12;
+/// Largest `word/document.xml` read from a DOCX; a small upload can inflate far past it.
+const MAX_DOCX_XML_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A document whose decompressed content exceeds the extraction limit.
+#[derive(Debug)]
+pub struct DecompressedTooLarge(u64);
+
+impl std::fmt::Display for DecompressedTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "document content exceeds {} bytes when decompressed", self.0)
+    }
+}
+
+impl std::error::Error for DecompressedTooLarge {}
+
+/// The client error for a failed [`extract_text`]: 413 past the decompression limit, 415 otherwise.
+pub fn extraction_error(e: anyhow::Error, prefix: &str) -> ApiError {
+    let message = format!("{prefix}: {e:#}");
+    if e.downcast_ref::<DecompressedTooLarge>().is_some() {
+        ApiError::PayloadTooLarge(message)
+    } else {
+        ApiError::UnsupportedMedia(message)
+    }
+}
+
+/// Formats text can be extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Text,
+}
+
+impl DocumentKind {
+    /// Detect the format from the file's leading bytes; anything else must be UTF-8 text.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"%PDF-") {
+            Some(DocumentKind::Pdf)
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            Some(DocumentKind::Docx)
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Some(DocumentKind::Text)
+        } else {
+            None
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            DocumentKind::Pdf => "application/pdf",
+            DocumentKind::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            DocumentKind::Text => "text/plain",
+        }
+    }
+}
+
+/// Extracted text, with paragraphs separated by blank lines where the format has them.
+/// PDF parsing is CPU-bound and can panic on malformed files: run it on a blocking thread.
+pub fn extract_text(kind: DocumentKind, bytes: &[u8]) -> Result<String> {
+    let text = match kind {
+        DocumentKind::Pdf => pdf_extract::extract_text_from_mem(bytes).context("unreadable PDF")?,
+        DocumentKind::Docx => docx_text(bytes, MAX_DOCX_XML_BYTES)?,
+        DocumentKind::Text => String::from_utf8(bytes.to_vec())?,
+    };
+    Ok(text.replace("\r\n", "\n").trim().to_string())
+}
+
+/// The body text of a DOCX (`word/document.xml`), reading at most `limit` decompressed bytes.
+fn docx_text(bytes: &[u8], limit: u64) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("unreadable DOCX")?;
+    let entry = archive
+        .by_name("word/document.xml")
+        .context("not a Word document: word/document.xml is missing")?;
+    // The declared size can lie, so the read is capped as well.
+    if entry.size() > limit {
+        return Err(DecompressedTooLarge(limit).into());
+    }
+    let mut xml = String::new();
+    entry.take(limit + 1).read_to_string(&mut xml)?;
+    if xml.len() as u64 > limit {
+        return Err(DecompressedTooLarge(limit).into());
+    }
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event().context("malformed word/document.xml")? {
+            Event::Start(e) if e.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(e) if e.name().as_ref() == b"w:t" => in_text = false,
+            Event::Text(t) if in_text => text.push_str(&t.unescape()?),
+            Event::Empty(e) if e.name().as_ref() == b"w:tab" => text.push('\t'),
+            Event::Empty(e) if e.name().as_ref() == b"w:br" => text.push('\n'),
+            Event::End(e) if e.name().as_ref() == b"w:p" => text.push_str("\n\n"),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// What is known about a stored document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentMeta {
+    pub id: Uuid,
+    pub filename: String,
+    pub kind: DocumentKind,
+    /// SHA-256 of the original bytes, as recorded in the audit ledger
+    pub sha256: String,
+    pub size: usize,
+    #[serde(with = "time::serde::rfc3339")]
+    pub uploaded_at: OffsetDateTime,
+}
+
+/// Encrypted sidecar holding the metadata and extracted text.
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    meta: DocumentMeta,
+    text: String,
+}
+
+pub struct DocumentStore {
+    dir: PathBuf,
+    cipher: Aes256Gcm,
+}
+
+impl DocumentStore {
+    pub fn new(storage_path: impl Into<PathBuf>, encryption_key: &str) -> Self {
+        let key = Sha256::digest(encryption_key.as_bytes());
+        Self {
+            dir: storage_path.into().join("documents"),
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        }
+    }
+
+    /// Encrypt and write the original and its sidecar; returns the new document's metadata.
+    pub async fn put(&self, filename: &str, kind: DocumentKind, original: &[u8], text: &str) -> Result<DocumentMeta> {
+        let meta = DocumentMeta {
+            id: Uuid::new_v4(),
+            filename: filename.to_string(),
+            kind,
+            sha256: format!("{:x}", Sha256::digest(original)),
+            size: original.len(),
+            uploaded_at: OffsetDateTime::now_utc(),
+        };
+        let sidecar = serde_json::to_vec(&Sidecar { meta: meta.clone(), text: text.to_string() })?;
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        for (path, plaintext) in [(self.original_path(meta.id), original), (self.sidecar_path(meta.id), &sidecar)] {
+            tokio::fs::write(&path, self.encrypt(plaintext)?)
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(meta)
+    }
+
+    /// Metadata and extracted text of document `id`, if it exists.
+    pub async fn text(&self, id: Uuid) -> Result<Option<(DocumentMeta, String)>> {
+        let path = self.sidecar_path(id);
+        let sealed = match tokio::fs::read(&path).await {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let sidecar: Sidecar = serde_json::from_slice(&self.decrypt(&sealed)?)?;
+        Ok(Some((sidecar.meta, sidecar.text)))
+    }
+
+    fn original_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.bin"))
+    }
+
+    fn sidecar_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.json.bin"))
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("encryption failed"))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            bail!("encrypted document is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("document could not be decrypted (wrong ENCRYPTION_KEY or corrupted file)"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// A minimal DOCX with the given paragraphs.
+    pub(crate) fn docx(paragraphs: &[&str]) -> Vec<u8> {
+        let body: String = paragraphs
+            .iter()
+            .map(|p| format!("<w:p><w:r><w:t xml:space=\"preserve\">{p}</w:t></w:r></w:p>"))
+            .collect();
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{body}</w:body></w:document>"#
+        );
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("word/document.xml", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(xml.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn extracts_docx_paragraphs() {
+        let bytes = docx(&["Art. 2043 c.c.", "Risarcimento &amp; danno"]);
+        assert_eq!(DocumentKind::detect(&bytes), Some(DocumentKind::Docx));
+        assert_eq!(
+            extract_text(DocumentKind::Docx, &bytes).unwrap(),
+            "Art. 2043 c.c.\n\nRisarcimento & danno"
+        );
+        assert_eq!(DocumentKind::detect(b"%PDF-1.7\n"), Some(DocumentKind::Pdf));
+        assert_eq!(DocumentKind::detect(&[0xff, 0xfe, 0x00]), None);
+        assert!(extract_text(DocumentKind::Pdf, b"%PDF-1.7 garbage").is_err());
+    }
+
+    #[test]
+    fn docx_decompression_is_capped() {
+        let bytes = docx(&[&"a".repeat(10_000)]);
+        assert!(bytes.len() < 1_000);
+        let err = docx_text(&bytes, 5_000).unwrap_err();
+        assert!(matches!(extraction_error(err, "atto.docx"), ApiError::PayloadTooLarge(_)));
+        assert!(docx_text(&bytes, 20_000).is_ok());
+
+        let err = extract_text(DocumentKind::Docx, b"PK\x03\x04 not a zip").unwrap_err();
+        assert!(matches!(extraction_error(err, "atto.docx"), ApiError::UnsupportedMedia(_)));
+    }
+
+    #[tokio::test]
+    async fn documents_are_encrypted_at_rest() {
+        let dir = TempDir::new().unwrap();
+        let store = DocumentStore::new(dir.path(), "0123456789abcdef0123456789abcdef");
+        let original = b"Sentenza n. 1234/2023 - Mario Rossi";
+        let meta = store.put("sentenza.txt", DocumentKind::Text, original, "Sentenza").await.unwrap();
+
+        for entry in std::fs::read_dir(dir.path().join("documents")).unwrap() {
+            let bytes = std::fs::read(entry.unwrap().path()).unwrap();
+            assert!(!bytes.windows(11).any(|w| w == b"Mario Rossi" || w == b"sentenza.tx"));
+        }
+        let sealed = std::fs::read(store.original_path(meta.id)).unwrap();
+        assert_eq!(store.decrypt(&sealed).unwrap(), original);
+        let (read, text) = store.text(meta.id).await.unwrap().unwrap();
+        assert_eq!((read.sha256, text.as_str()), (meta.sha256, "Sentenza"));
+        assert!(store.text(Uuid::nil()).await.unwrap().is_none());
+
+        let other_key = DocumentStore::new(dir.path(), "another-key-another-key-another-k");
+        assert!(other_key.text(meta.id).await.is_err());
+    }
+}
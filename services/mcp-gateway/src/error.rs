@@ -0,0 +1,251 @@
+//! API errors, returned as RFC 7807 `application/problem+json` bodies.
+//! `code` is stable and machine-readable: clients branch on it, never on `title` or `detail`.
+
+use axum::{
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::JsonRejection,
+        FromRequest,
+    },
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER},
+        HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// Malformed body or a field failing validation.
+    Validation {
+        detail: String,
+        errors: Option<serde_json::Value>,
+    },
+    /// Well-formed input rejected by a content policy.
+    PolicyViolation(String),
+    PayloadTooLarge(String),
+    /// An upload in a format text cannot be extracted from.
+    UnsupportedMedia(String),
+    NotFound(String),
+    Unauthorized,
+    RateLimited { retry_after_secs: u64 },
+    /// The online reasoner or another upstream service failed.
+    Upstream(String),
+    /// The local model is missing, unverified or failed to run.
+    ModelUnavailable(String),
+    /// Local log, ledger or file storage failed.
+    Storage(String),
+}
+
+/// Problem details body (RFC 7807) with the gateway's stable `code`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Problem {
+    /// `urn:counsel-ai:problem:<code>`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Stable machine-readable error code
+    pub code: String,
+    /// Per-field validation failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "validation_failed",
+            ApiError::PolicyViolation(_) => "policy_violation",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::UnsupportedMedia(_) => "unsupported_media_type",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::Upstream(_) => "upstream_failed",
+            ApiError::ModelUnavailable(_) => "model_unavailable",
+            ApiError::Storage(_) => "storage_failed",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Validation { .. } => StatusCode::BAD_REQUEST,
+            ApiError::PolicyViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedMedia(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ApiError::ModelUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "Invalid request",
+            ApiError::PolicyViolation(_) => "Content rejected by policy",
+            ApiError::PayloadTooLarge(_) => "Payload too large",
+            ApiError::UnsupportedMedia(_) => "Unsupported document format",
+            ApiError::NotFound(_) => "Not found",
+            ApiError::Unauthorized => "Missing or invalid API key",
+            ApiError::RateLimited { .. } => "Too many requests",
+            ApiError::Upstream(_) => "Upstream service failed",
+            ApiError::ModelUnavailable(_) => "Local model unavailable",
+            ApiError::Storage(_) => "Storage failed",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            ApiError::Validation { detail, .. }
+            | ApiError::PolicyViolation(detail)
+            | ApiError::PayloadTooLarge(detail)
+            | ApiError::UnsupportedMedia(detail)
+            | ApiError::NotFound(detail)
+            | ApiError::Upstream(detail)
+            | ApiError::ModelUnavailable(detail)
+            | ApiError::Storage(detail) => detail.clone(),
+            ApiError::Unauthorized => "Send `Authorization: Bearer <API_KEY>`".to_string(),
+            ApiError::RateLimited { retry_after_secs } => format!("Retry after {retry_after_secs}s"),
+        }
+    }
+
+    pub fn validation(detail: impl Into<String>) -> Self {
+        ApiError::Validation {
+            detail: detail.into(),
+            errors: None,
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        Problem {
+            problem_type: format!("urn:counsel-ai:problem:{}", self.code()),
+            title: self.title().to_string(),
+            status: self.status().as_u16(),
+            detail: self.detail(),
+            code: self.code().to_string(),
+            errors: match self {
+                ApiError::Validation { errors, .. } => errors.clone(),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiError::Validation {
+            detail: "Validation failed".to_string(),
+            errors: serde_json::to_value(&errors).ok(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::BytesRejection(_) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                ApiError::PayloadTooLarge(rejection.body_text())
+            }
+            _ => ApiError::validation(rejection.body_text()),
+        }
+    }
+}
+
+impl From<MultipartRejection> for ApiError {
+    fn from(rejection: MultipartRejection) -> Self {
+        ApiError::validation(rejection.body_text())
+    }
+}
+
+impl From<MultipartError> for ApiError {
+    fn from(error: MultipartError) -> Self {
+        if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            ApiError::PayloadTooLarge(error.body_text())
+        } else {
+            ApiError::validation(error.body_text())
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let problem = self.problem();
+        if self.status().is_server_error() {
+            tracing::error!(code = problem.code, "{}", problem.detail);
+        }
+        let mut response = (self.status(), Json(problem)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_CONTENT_TYPE));
+        if let ApiError::RateLimited { retry_after_secs } = self {
+            headers.insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
+    }
+}
+
+/// `Json` extractor whose rejections are [`ApiError`]s rather than plain-text bodies.
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    async fn problem_of(response: Response) -> (StatusCode, String, Problem) {
+        let status = response.status();
+        let content_type = response.headers()[CONTENT_TYPE].to_str().unwrap().to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn renders_problem_json_with_stable_code() {
+        let (status, content_type, problem) =
+            problem_of(ApiError::ModelUnavailable("model file missing".into()).into_response()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(content_type, PROBLEM_CONTENT_TYPE);
+        assert_eq!(problem.code, "model_unavailable");
+        assert_eq!(problem.problem_type, "urn:counsel-ai:problem:model_unavailable");
+        assert_eq!(problem.status, 503);
+        assert_eq!(problem.detail, "model file missing");
+
+        let response = ApiError::RateLimited { retry_after_secs: 3 }.into_response();
+        assert_eq!(response.headers()[RETRY_AFTER], "3");
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_validation_problem() {
+        let app = Router::new().route("/", post(|ApiJson(v): ApiJson<serde_json::Value>| async move { Json(v) }));
+        let response = app
+            .oneshot(
+                Request::post("/")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from("{not json"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let (status, _, problem) = problem_of(response).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem.code, "validation_failed");
+    }
+}
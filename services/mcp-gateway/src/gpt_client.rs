@@ -1,68 +1,297 @@
+use crate::config::LiveConfig;
+use crate::content_policy::{neutralize_markup, UNTRUSTED_NOTICE};
+use crate::error::ApiError;
+use crate::mcp_client::ToolHub;
+use crate::metrics::{Backend, Metrics};
 use crate::model::{ReasoningRequest, ReasoningResponse};
-use anyhow::{Context, Result};
+use crate::probes;
+use crate::redaction::redact_counted;
+use crate::signing::ResponseSigner;
+use anyhow::{bail, Context, Result};
 use llama_cpp::{Llama, LlamaOptions};
 use reqwest::Client;
 use serde_json::json;
-use std::path::Path;
-
-/// Calls GPT-5 through OpenAI API securely,
-/// or falls back to a local GGUF model (Mistral/Phi-3) if offline.
-pub async fn call_gpt(reason_req: &ReasoningRequest) -> Result<ReasoningResponse> {
-    if std::env::var("OPENAI_API_KEY").is_err() {
-        // Offline fallback
-        return call_local_model(reason_req);
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Used when `LOCAL_MODEL_PATH` is not set.
+const DEFAULT_LOCAL_MODEL: &str = "./models/mistral-7b-instruct.Q4_K_M.gguf";
+/// Model turns that may request tool calls before an answer is required.
+const MAX_TOOL_ROUNDS: usize = 4;
+
+/// Online (OpenAI-compatible) and local (llama.cpp) reasoning backends. Online settings
+/// are fixed at startup; the local model follows the live configuration.
+pub struct Reasoner {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    /// Read per request: the active local model can change on reload.
+    config: LiveConfig,
+    metrics: Arc<Metrics>,
+    /// Local MCP tools the online model may call.
+    tools: Arc<ToolHub>,
+    /// Signs every response with the gateway key.
+    signer: Arc<ResponseSigner>,
+    /// Local inference loads the whole model, so requests are run one at a time.
+    local_queue: Semaphore,
+}
+
+impl Reasoner {
+    pub fn new(
+        live: &LiveConfig,
+        client: Client,
+        metrics: Arc<Metrics>,
+        tools: Arc<ToolHub>,
+        signer: Arc<ResponseSigner>,
+    ) -> Self {
+        let config = live.get();
+        Self {
+            client,
+            base_url: config.openai_base_url.trim_end_matches('/').to_string(),
+            api_key: config.openai_api_key.clone(),
+            model: config.gpt_model.clone(),
+            config: live.clone(),
+            metrics,
+            tools,
+            signer,
+            local_queue: Semaphore::new(1),
+        }
+    }
+
+    /// The backend [`Reasoner::reason`] uses: online when an OpenAI key is configured.
+    pub fn backend(&self) -> Backend {
+        if self.api_key.is_some() {
+            Backend::Online
+        } else {
+            Backend::Local
+        }
+    }
+
+    /// Calls GPT-5 through OpenAI API securely,
+    /// or falls back to a local GGUF model (Mistral/Phi-3) if offline.
+    pub async fn reason(&self, reason_req: &ReasoningRequest) -> Result<ReasoningResponse, ApiError> {
+        let Some(api_key) = &self.api_key else {
+            // Offline fallback
+            return self.reason_locally(reason_req).await;
+        };
+
+        let result = self.call_online(api_key, reason_req).await;
+        self.metrics.record_reasoner(Backend::Online, result.is_ok());
+        let response = result.map_err(|e| ApiError::Upstream(format!("Online reasoning failed: {e:#}")))?;
+        self.signed(reason_req, response, &self.model, None)
+    }
+
+    fn signed(
+        &self,
+        req: &ReasoningRequest,
+        mut response: ReasoningResponse,
+        model: &str,
+        model_sha256: Option<String>,
+    ) -> Result<ReasoningResponse, ApiError> {
+        self.signer
+            .sign(req, &mut response, model, model_sha256)
+            .map_err(|e| ApiError::Storage(format!("Failed to sign the response: {e:#}")))?;
+        Ok(response)
+    }
+
+    /// Run the conversation, executing any tool calls the model makes on the local MCP
+    /// servers. Tool output is redacted before it is sent back to the model.
+    async fn call_online(&self, api_key: &str, reason_req: &ReasoningRequest) -> Result<ReasoningResponse> {
+        let tools = self.tools.function_definitions().await;
+        let mut messages = vec![
+            json!({"role": "system", "content": format!("You are a legal reasoning assistant for Counsel AI. You must never include private or identifying data. {UNTRUSTED_NOTICE}")}),
+            json!({"role": "user", "content": redact_counted(&self.metrics, &model_input(reason_req))}),
+        ];
+
+        for round in 0..=MAX_TOOL_ROUNDS {
+            let mut body = json!({ "model": self.model, "messages": messages });
+            // The last turn gets no tools, so the model has to answer.
+            if round < MAX_TOOL_ROUNDS && !tools.is_empty() {
+                body["tools"] = json!(tools);
+            }
+            let message = self.complete(api_key, &body).await?;
+
+            let calls = message["tool_calls"].as_array().cloned().unwrap_or_default();
+            if calls.is_empty() {
+                return Ok(ReasoningResponse::new(neutralize_markup(
+                    message["content"].as_str().unwrap_or_default().to_string(),
+                )));
+            }
+            messages.push(message);
+            for call in calls {
+                let name = call["function"]["name"].as_str().unwrap_or_default();
+                let arguments = call["function"]["arguments"]
+                    .as_str()
+                    .and_then(|args| serde_json::from_str(args).ok())
+                    .unwrap_or_else(|| json!({}));
+                let output = self.tools.call(name, arguments).await;
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": call["id"],
+                    "content": redact_counted(&self.metrics, &output),
+                }));
+            }
+        }
+        bail!("model kept requesting tools after {MAX_TOOL_ROUNDS} rounds")
     }
 
-    let api_key = std::env::var("OPENAI_API_KEY")
-        .context("OPENAI_API_KEY is required for online reasoning")?;
-    let model = std::env::var("GPT_MODEL").unwrap_or_else(|_| "gpt-5".to_string());
-
-    let client = Client::new();
-    let body = json!({
-        "model": model,
-        "messages": [
-            {"role": "system", "content": "You are a legal reasoning assistant for Counsel AI. You must never include private or identifying data."},
-            {"role": "user", "content": reason_req.prompt}
-        ]
-    });
-
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .bearer_auth(api_key)
-        .json(&body)
-        .send()
-        .await
-        .context("failed to contact OpenAI")?;
-
-    let value: serde_json::Value = response.json().await.context("invalid OpenAI response")?;
-    let text = value["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or_default()
-        .to_string();
-
-    Ok(ReasoningResponse {
-        summary: text,
-        citations: vec![],
-    })
+    /// One chat completion; returns the assistant message.
+    async fn complete(&self, api_key: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        let response = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(api_key)
+            .json(body)
+            .send()
+            .await
+            .context("failed to contact OpenAI")?
+            .error_for_status()
+            .context("OpenAI rejected the request")?;
+
+        let mut value: serde_json::Value = response.json().await.context("invalid OpenAI response")?;
+        if let Some(usage) = value.get("usage") {
+            self.metrics.record_tokens(
+                Backend::Online,
+                usage["prompt_tokens"].as_u64().unwrap_or(0),
+                usage["completion_tokens"].as_u64().unwrap_or(0),
+            );
+        }
+        Ok(value["choices"][0]["message"].take())
+    }
+
+    /// Queue a request for the local model and run it off the async runtime.
+    pub async fn reason_locally(&self, req: &ReasoningRequest) -> Result<ReasoningResponse, ApiError> {
+        let _queued = self.metrics.enqueue_local();
+        let _permit = self
+            .local_queue
+            .acquire()
+            .await
+            .map_err(|_| ApiError::ModelUnavailable("Local inference queue closed".to_string()))?;
+        let model_path = PathBuf::from(
+            self.config
+                .get()
+                .local_model_path
+                .as_deref()
+                .unwrap_or(DEFAULT_LOCAL_MODEL),
+        );
+        if !model_path.is_file() {
+            self.metrics.record_reasoner(Backend::Local, false);
+            return Err(ApiError::ModelUnavailable(format!(
+                "Local model not found at {}",
+                model_path.display()
+            )));
+        }
+        let model = model_path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+        let model_sha256 = probes::trusted_sha256(self.config.get().registry_dir.as_deref(), &model_path);
+        let task_req = ReasoningRequest {
+            prompt: req.prompt.clone(),
+            context: req.context.clone(),
+            truncated: Vec::new(),
+        };
+        let result = tokio::task::spawn_blocking(move || call_local_model(&model_path, &task_req))
+            .await
+            .context("local inference task failed")
+            .and_then(|r| r);
+        self.metrics.record_reasoner(Backend::Local, result.is_ok());
+        let response = result.map_err(|e| ApiError::ModelUnavailable(format!("Local model error: {e:#}")))?;
+        self.signed(req, response, &model, model_sha256)
+    }
+}
+
+/// The prompt followed by its context segments, which arrive fenced by the content policy.
+fn model_input(req: &ReasoningRequest) -> String {
+    std::iter::once(req.prompt.as_str())
+        .chain(req.context.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n\n")
 }
 
 /// Offline reasoning using a local GGUF model (Mistral or Phi-3)
-pub fn call_local_model(req: &ReasoningRequest) -> Result<ReasoningResponse> {
-    let model_path = std::env::var("LOCAL_MODEL_PATH")
-        .unwrap_or_else(|_| "./models/mistral-7b-instruct.Q4_K_M.gguf".to_string());
+fn call_local_model(model_path: &Path, req: &ReasoningRequest) -> Result<ReasoningResponse> {
     let prompt = format!(
-        "You are an Italian legal assistant. Summarize and analyze this case: {}",
-        req.prompt
+        "You are an Italian legal assistant. {UNTRUSTED_NOTICE} Summarize and analyze this case: {}",
+        model_input(req)
     );
 
-    let mut llama = Llama::new_with_options(
-        Path::new(&model_path),
-        LlamaOptions::default().n_threads(4),
-    )?;
+    let mut llama = Llama::new_with_options(model_path, LlamaOptions::default().n_threads(4))?;
 
     let response = llama.run(&prompt, None)?;
-    Ok(ReasoningResponse {
-        summary: response.trim().to_string(),
-        citations: vec![],
-    })
+    Ok(ReasoningResponse::new(neutralize_markup(response.trim().to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        ledger::Ledger,
+        mcp_client,
+        signing::{self, SignatureStatus},
+    };
+    use axum::{extract::State, routing::post, Json, Router};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// An OpenAI stand-in that asks for one vault lookup, then answers.
+    async fn fake_openai(seen: Arc<Mutex<Vec<serde_json::Value>>>) -> String {
+        let app = Router::new()
+            .route(
+                "/chat/completions",
+                post(|State(seen): State<Arc<Mutex<Vec<serde_json::Value>>>>, Json(body): Json<serde_json::Value>| async move {
+                    let mut seen = seen.lock().unwrap();
+                    seen.push(body);
+                    let message = if seen.len() == 1 {
+                        json!({ "role": "assistant", "content": null, "tool_calls": [{
+                            "id": "call_1", "type": "function",
+                            "function": { "name": "vault__vault_get", "arguments": "{\"id\":\"c-1\"}" }
+                        }]})
+                    } else {
+                        json!({ "role": "assistant", "content": "Parere pronto." })
+                    };
+                    Json(json!({ "choices": [{ "message": message }] }))
+                }),
+            )
+            .with_state(seen);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn tool_results_are_redacted_before_reaching_the_model() {
+        let dir = TempDir::new().unwrap();
+        let seen = Arc::default();
+        let live = LiveConfig::new(Config {
+            openai_api_key: Some("sk-test".into()),
+            openai_base_url: fake_openai(Arc::clone(&seen)).await,
+            ..Config::default()
+        });
+        let metrics = Arc::new(Metrics::new());
+        let ledger = Arc::new(Ledger::new(dir.path().join("ledger.jsonl")));
+        let tools = Arc::new(ToolHub::new(
+            vec![mcp_client::tests::fake_vault_server()],
+            Client::new(),
+            Arc::clone(&ledger),
+            Arc::clone(&metrics),
+        ));
+        let signer = Arc::new(signing::tests::signer());
+        let reasoner = Reasoner::new(&live, Client::new(), metrics, tools, Arc::clone(&signer));
+
+        let answer = reasoner
+            .reason(&ReasoningRequest { prompt: "Parere sul cliente c-1".into(), context: vec![], truncated: vec![] })
+            .await
+            .unwrap();
+        assert_eq!(answer.summary, "Parere pronto.");
+        assert_eq!(signer.verify(&answer), SignatureStatus::Valid);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0]["tools"][0]["function"]["name"], "vault__vault_get");
+        let tool_message = &seen[1]["messages"][3];
+        assert_eq!(tool_message["role"], "tool");
+        assert_eq!(tool_message["content"], "Client: [EMAIL]");
+        assert_eq!(ledger.verify().unwrap().records, 1);
+    }
 }
@@ -1,77 +1,274 @@
-use axum::{http::StatusCode, Json};
-use serde_json::json;
-use std::time::{Duration, Instant};
-use tokio::time::timeout;
+use axum::{extract::State, http::StatusCode, response::Response, Json};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{task::JoinSet, time::timeout};
 use utoipa::ToSchema;
 
+use crate::{
+    config::LiveConfig,
+    ledger::Ledger,
+    metrics,
+    probes::{DiskSpaceProbe, LedgerProbe, LocalModelProbe, QdrantProbe, ReasonerProbe},
+    state::AppState,
+};
+
 /// Health check response structure
-#[derive(serde::Serialize, ToSchema)]
+#[derive(Serialize, ToSchema)]
 pub struct HealthResponse {
-    pub status: String,
+    pub status: ProbeStatus,
     pub timestamp: String,
     pub version: String,
     pub uptime: u64,
-    pub checks: HealthChecks,
+    /// Probe results keyed by probe name
+    pub checks: BTreeMap<String, CheckResult>,
 }
 
-#[derive(serde::Serialize, ToSchema)]
-pub struct HealthChecks {
-    pub database: CheckResult,
-    pub storage: CheckResult,
-    pub external_apis: CheckResult,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
 }
 
-#[derive(serde::Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct CheckResult {
-    pub status: String,
+    pub status: ProbeStatus,
+    pub message: String,
+    pub response_time_ms: u64,
+    /// When the probe last actually ran (RFC 3339)
+    pub checked_at: String,
+    /// Whether this result was served from the TTL cache
+    pub cached: bool,
+}
+
+/// What a probe reports before timing and caching are applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeOutcome {
+    pub status: ProbeStatus,
     pub message: String,
-    pub response_time_ms: Option<u64>,
 }
 
-/// Global start time for uptime calculation
-static mut START_TIME: Option<Instant> = None;
+impl ProbeOutcome {
+    pub fn healthy(message: impl Into<String>) -> Self {
+        Self { status: ProbeStatus::Healthy, message: message.into() }
+    }
 
-pub fn init() {
-    unsafe {
-        START_TIME = Some(Instant::now());
+    pub fn degraded(message: impl Into<String>) -> Self {
+        Self { status: ProbeStatus::Degraded, message: message.into() }
+    }
+
+    pub fn unhealthy(message: impl Into<String>) -> Self {
+        Self { status: ProbeStatus::Unhealthy, message: message.into() }
     }
 }
 
-pub fn get_uptime() -> u64 {
-    unsafe {
-        START_TIME
-            .map(|start| start.elapsed().as_secs())
-            .unwrap_or(0)
+pub type ProbeFuture<'a> = Pin<Box<dyn Future<Output = ProbeOutcome> + Send + 'a>>;
+
+/// A single dependency check; see `probes.rs` for the built-in ones.
+pub trait HealthProbe: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Critical probes report unhealthy rather than degraded when they time out.
+    fn critical(&self) -> bool {
+        false
     }
+
+    fn check(&self) -> ProbeFuture<'_>;
 }
 
-/// Comprehensive health check endpoint
-pub async fn health_check() -> (StatusCode, Json<HealthResponse>) {
-    let start_time = Instant::now();
-    let mut checks = HealthChecks {
-        database: check_database().await,
-        storage: check_storage().await,
-        external_apis: check_external_apis().await,
-    };
+/// Runs registered probes concurrently and caches each result for `ttl`, so
+/// frequent `/health` calls cannot hammer upstream services.
+pub struct HealthRegistry {
+    probes: Vec<Arc<dyn HealthProbe>>,
+    ttl: Duration,
+    timeout: Duration,
+    cache: tokio::sync::Mutex<HashMap<&'static str, (Instant, CheckResult)>>,
+    /// Probes that must be healthy for `/readyz`.
+    readiness: Vec<String>,
+    /// Probes that must be healthy once for `/startupz`.
+    startup: Vec<String>,
+    started: AtomicBool,
+}
 
-    // Determine overall status
-    let overall_status = if checks.database.status == "healthy" 
-        && checks.storage.status == "healthy" 
-        && checks.external_apis.status != "critical" {
-        "healthy"
-    } else {
-        "degraded"
-    };
+impl HealthRegistry {
+    pub fn new(ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            probes: Vec::new(),
+            ttl,
+            timeout,
+            cache: tokio::sync::Mutex::new(HashMap::new()),
+            readiness: Vec::new(),
+            startup: Vec::new(),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_criteria(mut self, readiness: Vec<String>, startup: Vec<String>) -> Self {
+        self.readiness = readiness;
+        self.startup = startup;
+        self
+    }
+
+    pub fn with_probe(mut self, probe: impl HealthProbe + 'static) -> Self {
+        self.probes.push(Arc::new(probe));
+        self
+    }
+
+    /// The built-in probes for the configured dependencies.
+    pub fn from_config(live: &LiveConfig, client: reqwest::Client, ledger: Arc<Ledger>) -> Self {
+        let config = live.get();
+        Self::new(
+            Duration::from_secs(config.health_cache_ttl_secs),
+            Duration::from_secs(config.health_probe_timeout_secs),
+        )
+        .with_probe(QdrantProbe {
+            client: client.clone(),
+            url: config.vector_db_url.clone(),
+        })
+        .with_probe(ReasonerProbe {
+            client,
+            base_url: config.openai_base_url.clone(),
+            api_key: config.openai_api_key.clone(),
+        })
+        .with_probe(LocalModelProbe { config: live.clone() })
+        .with_probe(LedgerProbe { ledger })
+        .with_probe(DiskSpaceProbe {
+            path: PathBuf::from(&config.storage_path),
+            min_free_bytes: config.min_free_disk_mb * 1024 * 1024,
+        })
+        .with_criteria(config.readiness_checks.clone(), config.startup_checks.clone())
+    }
+
+    pub async fn check_all(&self) -> BTreeMap<String, CheckResult> {
+        self.check(None).await
+    }
+
+    /// Current result of the named probes (or all of them), re-running only those whose
+    /// cache entry expired. The cache lock is held while probes run, so concurrent
+    /// callers share one refresh.
+    async fn check(&self, only: Option<&[String]>) -> BTreeMap<String, CheckResult> {
+        let selected = |name: &str| match only {
+            Some(names) => names.iter().any(|n| n == name),
+            None => true,
+        };
+        let mut cache = self.cache.lock().await;
+        let mut running = JoinSet::new();
+        for probe in self.probes.iter().filter(|p| selected(p.name())) {
+            let fresh = cache
+                .get(probe.name())
+                .is_some_and(|(at, _)| at.elapsed() < self.ttl);
+            if fresh {
+                continue;
+            }
+            let probe = Arc::clone(probe);
+            let limit = self.timeout;
+            running.spawn(async move {
+                let started = Instant::now();
+                let outcome = match timeout(limit, probe.check()).await {
+                    Ok(outcome) => outcome,
+                    Err(_) => ProbeOutcome {
+                        status: if probe.critical() { ProbeStatus::Unhealthy } else { ProbeStatus::Degraded },
+                        message: format!("Timed out after {}s", limit.as_secs()),
+                    },
+                };
+                let result = CheckResult {
+                    status: outcome.status,
+                    message: outcome.message,
+                    response_time_ms: started.elapsed().as_millis() as u64,
+                    checked_at: chrono::Utc::now().to_rfc3339(),
+                    cached: false,
+                };
+                (probe.name(), result)
+            });
+        }
+
+        let mut checks = BTreeMap::new();
+        while let Some(joined) = running.join_next().await {
+            if let Ok((name, result)) = joined {
+                cache.insert(name, (Instant::now(), result.clone()));
+                checks.insert(name.to_string(), result);
+            }
+        }
+        for (name, (_, result)) in cache.iter().filter(|(name, _)| selected(name)) {
+            checks.entry(name.to_string()).or_insert_with(|| CheckResult {
+                cached: true,
+                ..result.clone()
+            });
+        }
+        checks
+    }
+
+    /// Run the given criteria; passes when every listed probe is healthy.
+    async fn evaluate(&self, criteria: &[String]) -> ProbeResponse {
+        let checks = self.check(Some(criteria)).await;
+        let ok = checks.values().all(|c| c.status == ProbeStatus::Healthy);
+        ProbeResponse { ok, checks }
+    }
+
+    pub async fn readiness(&self) -> ProbeResponse {
+        self.evaluate(&self.readiness).await
+    }
+
+    /// Latches: once the startup criteria have passed, later calls succeed without re-checking.
+    pub async fn startup(&self) -> ProbeResponse {
+        if self.started.load(Ordering::Acquire) {
+            return ProbeResponse { ok: true, checks: BTreeMap::new() };
+        }
+        let response = self.evaluate(&self.startup).await;
+        if response.ok {
+            self.started.store(true, Ordering::Release);
+        }
+        response
+    }
+}
+
+/// Body of `/livez`, `/readyz` and `/startupz`.
+#[derive(Serialize, ToSchema)]
+pub struct ProbeResponse {
+    pub ok: bool,
+    /// Results of the probes that make up this criterion
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+/// Detailed diagnostic report of every dependency probe.
+/// Degraded optional dependencies still answer 200; use `/readyz` for routing decisions.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "counsel-ai",
+    responses(
+        (status = 200, description = "No critical dependency is unhealthy", body = HealthResponse),
+        (status = 503, description = "A critical dependency is unhealthy", body = HealthResponse)
+    )
+)]
+pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let checks = state.health.check_all().await;
+    let overall_status = checks
+        .values()
+        .map(|c| c.status)
+        .max()
+        .unwrap_or(ProbeStatus::Healthy);
 
     let response = HealthResponse {
-        status: overall_status.to_string(),
+        status: overall_status,
         timestamp: chrono::Utc::now().to_rfc3339(),
         version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: get_uptime(),
+        uptime: state.metrics.uptime().as_secs(),
         checks,
     };
 
-    let status_code = if overall_status == "healthy" {
+    let status_code = if overall_status != ProbeStatus::Unhealthy {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
@@ -80,121 +277,177 @@ pub async fn health_check() -> (StatusCode, Json<HealthResponse>) {
     (status_code, Json(response))
 }
 
-/// Check database connectivity
-async fn check_database() -> CheckResult {
-    let start = Instant::now();
-    
-    // Simulate database check (replace with actual implementation)
-    match timeout(Duration::from_secs(5), async {
-        // Add actual database health check here
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        Ok::<(), String>(())
-    }).await {
-        Ok(Ok(_)) => CheckResult {
-            status: "healthy".to_string(),
-            message: "Database connection successful".to_string(),
-            response_time_ms: Some(start.elapsed().as_millis() as u64),
-        },
-        Ok(Err(e)) => CheckResult {
-            status: "unhealthy".to_string(),
-            message: format!("Database error: {}", e),
-            response_time_ms: Some(start.elapsed().as_millis() as u64),
-        },
-        Err(_) => CheckResult {
-            status: "unhealthy".to_string(),
-            message: "Database connection timeout".to_string(),
-            response_time_ms: Some(5000),
-        },
-    }
-}
-
-/// Check storage accessibility
-async fn check_storage() -> CheckResult {
-    let start = Instant::now();
-    
-    match timeout(Duration::from_secs(3), async {
-        // Check if storage directory is accessible
-        let storage_path = std::env::var("STORAGE_PATH").unwrap_or_else(|_| "/data/encrypted".to_string());
-        tokio::fs::metadata(&storage_path).await.map_err(|e| e.to_string())
-    }).await {
-        Ok(Ok(_)) => CheckResult {
-            status: "healthy".to_string(),
-            message: "Storage accessible".to_string(),
-            response_time_ms: Some(start.elapsed().as_millis() as u64),
-        },
-        Ok(Err(e)) => CheckResult {
-            status: "unhealthy".to_string(),
-            message: format!("Storage error: {}", e),
-            response_time_ms: Some(start.elapsed().as_millis() as u64),
-        },
-        Err(_) => CheckResult {
-            status: "unhealthy".to_string(),
-            message: "Storage check timeout".to_string(),
-            response_time_ms: Some(3000),
-        },
-    }
-}
-
-/// Check external API dependencies
-async fn check_external_apis() -> CheckResult {
-    let start = Instant::now();
-    
-    // Check OpenAI API if configured
-    let openai_key = std::env::var("OPENAI_API_KEY").ok();
-    if openai_key.is_some() {
-        match timeout(Duration::from_secs(5), async {
-            // Add actual OpenAI API health check here
-            tokio::time::sleep(Duration::from_millis(50)).await;
-            Ok::<(), String>(())
-        }).await {
-            Ok(Ok(_)) => CheckResult {
-                status: "healthy".to_string(),
-                message: "External APIs accessible".to_string(),
-                response_time_ms: Some(start.elapsed().as_millis() as u64),
-            },
-            Ok(Err(e)) => CheckResult {
-                status: "degraded".to_string(),
-                message: format!("External API error: {}", e),
-                response_time_ms: Some(start.elapsed().as_millis() as u64),
-            },
-            Err(_) => CheckResult {
-                status: "degraded".to_string(),
-                message: "External API timeout".to_string(),
-                response_time_ms: Some(5000),
-            },
+/// Liveness: the process is up and serving requests. Never checks dependencies,
+/// so a slow upstream cannot get a healthy gateway restarted.
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "counsel-ai",
+    responses((status = 200, description = "Process is alive", body = ProbeResponse))
+)]
+pub async fn livez() -> Json<ProbeResponse> {
+    Json(ProbeResponse { ok: true, checks: BTreeMap::new() })
+}
+
+/// Readiness: the probes listed in `READINESS_CHECKS` are healthy.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "counsel-ai",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ProbeResponse),
+        (status = 503, description = "Not ready", body = ProbeResponse)
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ProbeResponse>) {
+    probe_reply(state.health.readiness().await)
+}
+
+/// Startup: the probes listed in `STARTUP_CHECKS` have passed at least once.
+#[utoipa::path(
+    get,
+    path = "/startupz",
+    tag = "counsel-ai",
+    responses(
+        (status = 200, description = "Startup complete", body = ProbeResponse),
+        (status = 503, description = "Still starting", body = ProbeResponse)
+    )
+)]
+pub async fn startupz(State(state): State<AppState>) -> (StatusCode, Json<ProbeResponse>) {
+    probe_reply(state.health.startup().await)
+}
+
+fn probe_reply(response: ProbeResponse) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if response.ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(response))
+}
+
+/// Metrics endpoint for monitoring
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "counsel-ai",
+    security(("bearer_auth" = []), ()),
+    responses(
+        (status = 200, description = "Prometheus text exposition", content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid API key, unless `metrics_public` is set", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    metrics::exposition(&state.metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting {
+        calls: Arc<AtomicUsize>,
+        delay: Duration,
+        critical: bool,
+    }
+
+    impl HealthProbe for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
         }
-    } else {
-        CheckResult {
-            status: "healthy".to_string(),
-            message: "No external APIs configured".to_string(),
-            response_time_ms: Some(start.elapsed().as_millis() as u64),
+
+        fn critical(&self) -> bool {
+            self.critical
+        }
+
+        fn check(&self) -> ProbeFuture<'_> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(self.delay).await;
+                ProbeOutcome::healthy("ok")
+            })
         }
     }
-}
 
-/// Metrics endpoint for monitoring
-pub async fn metrics() -> (StatusCode, String) {
-    let metrics = format!(
-        "# HELP counsel_ai_uptime_seconds Total uptime in seconds
-# TYPE counsel_ai_uptime_seconds counter
-counsel_ai_uptime_seconds {{}} {}
-
-# HELP counsel_ai_requests_total Total number of requests
-# TYPE counsel_ai_requests_total counter
-counsel_ai_requests_total {{}} 0
-
-# HELP counsel_ai_request_duration_seconds Request duration in seconds
-# TYPE counsel_ai_request_duration_seconds histogram
-counsel_ai_request_duration_seconds_bucket {{le=\"0.1\"}} 0
-counsel_ai_request_duration_seconds_bucket {{le=\"0.5\"}} 0
-counsel_ai_request_duration_seconds_bucket {{le=\"1.0\"}} 0
-counsel_ai_request_duration_seconds_bucket {{le=\"5.0\"}} 0
-counsel_ai_request_duration_seconds_bucket {{le=\"+Inf\"}} 0
-counsel_ai_request_duration_seconds_sum {{}} 0
-counsel_ai_request_duration_seconds_count {{}} 0
-",
-        get_uptime()
-    );
-    
-    (StatusCode::OK, metrics)
-}
\ No newline at end of file
+    fn registry(ttl: Duration, delay: Duration, critical: bool) -> (HealthRegistry, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let registry = HealthRegistry::new(ttl, Duration::from_millis(50)).with_probe(Counting {
+            calls: Arc::clone(&calls),
+            delay,
+            critical,
+        });
+        (registry, calls)
+    }
+
+    #[tokio::test]
+    async fn results_are_cached_until_ttl_expires() {
+        let (registry, calls) = registry(Duration::from_millis(100), Duration::ZERO, false);
+
+        let first = registry.check_all().await;
+        assert!(!first["counting"].cached);
+        let second = registry.check_all().await;
+        assert!(second["counting"].cached);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert!(!registry.check_all().await["counting"].cached);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_refresh() {
+        let (registry, calls) = registry(Duration::from_secs(60), Duration::from_millis(20), false);
+        tokio::join!(registry.check_all(), registry.check_all(), registry.check_all());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn timeouts_degrade_or_fail_by_criticality() {
+        let (optional, _) = registry(Duration::ZERO, Duration::from_secs(5), false);
+        assert_eq!(optional.check_all().await["counting"].status, ProbeStatus::Degraded);
+
+        let (critical, _) = registry(Duration::ZERO, Duration::from_secs(5), true);
+        assert_eq!(critical.check_all().await["counting"].status, ProbeStatus::Unhealthy);
+    }
+
+    struct Switch {
+        name: &'static str,
+        healthy: Arc<AtomicBool>,
+    }
+
+    impl HealthProbe for Switch {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn check(&self) -> ProbeFuture<'_> {
+            Box::pin(async move {
+                if self.healthy.load(Ordering::SeqCst) {
+                    ProbeOutcome::healthy("up")
+                } else {
+                    ProbeOutcome::degraded("down")
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn readiness_only_considers_its_criteria_and_startup_latches() {
+        let ledger = Arc::new(AtomicBool::new(false));
+        let qdrant = Arc::new(AtomicBool::new(false));
+        let registry = HealthRegistry::new(Duration::ZERO, Duration::from_secs(1))
+            .with_probe(Switch { name: "ledger", healthy: Arc::clone(&ledger) })
+            .with_probe(Switch { name: "vector_db", healthy: Arc::clone(&qdrant) })
+            .with_criteria(vec!["ledger".into()], vec!["ledger".into()]);
+
+        let not_ready = registry.readiness().await;
+        assert!(!not_ready.ok);
+        assert_eq!(not_ready.checks.keys().collect::<Vec<_>>(), ["ledger"]);
+        assert!(!registry.startup().await.ok);
+
+        ledger.store(true, Ordering::SeqCst);
+        assert!(registry.readiness().await.ok, "vector_db is not a readiness criterion");
+        assert!(registry.startup().await.ok);
+
+        ledger.store(false, Ordering::SeqCst);
+        assert!(!registry.readiness().await.ok);
+        assert!(registry.startup().await.ok, "startup stays complete once reached");
+    }
+}
@@ -0,0 +1,112 @@
+//! Read-only access to the legal knowledge base kept in Qdrant (`vector_db_url`,
+//! collection `kb_collection`). Search is full-text over each point's `text` payload,
+//! so the gateway needs no embedding model of its own.
+//!
+//! Points describing a source also carry keyword payload used to resolve citations
+//! (values as in [`crate::citations`]):
+//! - `kind: "code_article"`, `code`, `article` (e.g. `"2050-bis"`)
+//! - `kind: "legislation"`, `act`, `number`, `year`, optionally `article`
+//! - `kind: "case_law"`, `court`, `number`, `year`, optionally `division` and `section`
+//!
+//! and `status: "repealed"` (with `repealed_by`) for provisions no longer in force.
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::{config::Config, model::KbHit};
+
+pub const DEFAULT_LIMIT: usize = 5;
+
+pub struct KnowledgeBase {
+    client: Client,
+    base_url: String,
+    collection: String,
+}
+
+impl KnowledgeBase {
+    pub fn new(config: &Config, client: Client) -> Self {
+        Self {
+            client,
+            base_url: config.vector_db_url.trim_end_matches('/').to_string(),
+            collection: config.kb_collection.clone(),
+        }
+    }
+
+    /// Passages whose `text` matches `query`, in collection order.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<KbHit>> {
+        let filter = json!({ "must": [{ "key": "text", "match": { "text": query } }] });
+        let points = self.scroll(filter, limit).await?;
+        Ok(points.iter().filter_map(hit).collect())
+    }
+
+    /// Id and payload of the points whose payload has exactly these `fields`.
+    pub async fn lookup(&self, fields: &[(&str, Value)], limit: usize) -> Result<Vec<(String, Value)>> {
+        let must: Vec<Value> = fields
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "match": { "value": value } }))
+            .collect();
+        let points = self.scroll(json!({ "must": must }), limit).await?;
+        Ok(points.into_iter().map(|mut point| (point_id(&point), point["payload"].take())).collect())
+    }
+
+    async fn scroll(&self, filter: Value, limit: usize) -> Result<Vec<Value>> {
+        let body = json!({
+            "filter": filter,
+            "limit": limit,
+            "with_payload": true,
+            "with_vector": false,
+        });
+        let mut response: Value = self
+            .client
+            .post(format!("{}/collections/{}/points/scroll", self.base_url, self.collection))
+            .json(&body)
+            .send()
+            .await
+            .context("failed to contact the knowledge base")?
+            .error_for_status()
+            .context("knowledge base rejected the search")?
+            .json()
+            .await
+            .context("invalid knowledge base response")?;
+
+        Ok(match response["result"]["points"].take() {
+            Value::Array(points) => points,
+            _ => Vec::new(),
+        })
+    }
+}
+
+fn point_id(point: &Value) -> String {
+    match &point["id"] {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+/// A Qdrant point as a [`KbHit`]; points without a `text` payload are skipped.
+fn hit(point: &Value) -> Option<KbHit> {
+    let payload = &point["payload"];
+    Some(KbHit {
+        id: point_id(point),
+        text: payload["text"].as_str()?.to_string(),
+        source: payload["source"].as_str().map(String::from),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_map_to_hits() {
+        let numbered = hit(&json!({ "id": 7, "payload": { "text": "Art. 2043 c.c.", "source": "codice civile" } })).unwrap();
+        assert_eq!((numbered.id.as_str(), numbered.source.as_deref()), ("7", Some("codice civile")));
+
+        let uuid = hit(&json!({ "id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26", "payload": { "text": "…" } })).unwrap();
+        assert_eq!(uuid.id, "5c56c793-69f3-4fbf-87e6-c4bf54c28c26");
+        assert!(uuid.source.is_none());
+
+        assert!(hit(&json!({ "id": 1, "payload": {} })).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+//! Hash-chained audit ledger (JSON lines).
+//! Records use the same format as `counsel_ai/audit.py`, so ledgers written by either
+//! side verify with the other: `data_hash = sha256(json.dumps({action, payload}, sort_keys=True))`
+//! and `chain_hash = sha256(prev_hash + data_hash)`, starting from 64 zeros.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerRecord {
+    pub timestamp: String,
+    pub action: String,
+    pub data_hash: String,
+    pub prev_hash: String,
+    pub chain_hash: String,
+}
+
+/// Outcome of a full chain verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LedgerReport {
+    pub records: usize,
+    pub head: String,
+}
+
+pub struct Ledger {
+    path: PathBuf,
+    /// Chain hash of the last record, loaded lazily; also serialises appends.
+    head: Mutex<Option<String>>,
+}
+
+impl Ledger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            head: Mutex::new(None),
+        }
+    }
+
+    /// Append a record for `action` and return it once it is synced to disk.
+    pub fn append(&self, action: &str, payload: &Value) -> Result<LedgerRecord> {
+        let mut head = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        let prev_hash = match head.as_ref() {
+            Some(hash) => hash.clone(),
+            None => last_chain_hash(&self.path)?,
+        };
+
+        let data_hash = sha256_hex(&python_json(&json!({ "action": action, "payload": payload })));
+        let record = LedgerRecord {
+            timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.6f").to_string(),
+            action: action.to_string(),
+            chain_hash: chain_hash(&prev_hash, &data_hash),
+            data_hash,
+            prev_hash,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open ledger {}", self.path.display()))?;
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        *head = Some(record.chain_hash.clone());
+        Ok(record)
+    }
+
+    /// Re-walk the whole chain. A missing ledger is an empty, valid chain.
+    pub fn verify(&self) -> Result<LedgerReport> {
+        let _guard = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        verify_file(&self.path)
+    }
+}
+
+fn verify_file(path: &Path) -> Result<LedgerReport> {
+    let mut report = LedgerReport {
+        records: 0,
+        head: GENESIS_HASH.to_string(),
+    };
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e).with_context(|| format!("failed to open ledger {}", path.display())),
+    };
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: LedgerRecord =
+            serde_json::from_str(&line).with_context(|| format!("invalid record at line {}", index + 1))?;
+        if record.prev_hash != report.head {
+            bail!("broken link at line {}: prev_hash does not match previous record", index + 1);
+        }
+        if record.chain_hash != chain_hash(&report.head, &record.data_hash) {
+            bail!("hash mismatch at line {}", index + 1);
+        }
+        report.head = record.chain_hash;
+        report.records += 1;
+    }
+    Ok(report)
+}
+
+fn last_chain_hash(path: &Path) -> Result<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(GENESIS_HASH.to_string()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read ledger {}", path.display())),
+    };
+    match content.lines().rev().find(|l| !l.trim().is_empty()) {
+        Some(line) => Ok(serde_json::from_str::<LedgerRecord>(line)
+            .context("last ledger record is not valid")?
+            .chain_hash),
+        None => Ok(GENESIS_HASH.to_string()),
+    }
+}
+
+fn sha256_hex(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+fn chain_hash(prev_hash: &str, data_hash: &str) -> String {
+    sha256_hex(&format!("{prev_hash}{data_hash}"))
+}
+
+/// Serialise like Python's `json.dumps(value, sort_keys=True)`: `", "` and `": "`
+/// separators and ASCII-only output with `\uXXXX` escapes.
+fn python_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_python_json(value, &mut out);
+    out
+}
+
+fn write_python_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => write_python_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_python_json(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_python_string(key, out);
+                out.push_str(": ");
+                write_python_json(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_python_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn data_hash_matches_python_audit_module() {
+        // python3 -c 'json.dumps({"action": "store", "payload": ...}, sort_keys=True)'
+        let payload = json!({ "b": [1, 2.5, null, true], "a": "caffè ✓ 😀", "z": { "y": 1, "x": "q\"\n" } });
+        let raw = python_json(&json!({ "action": "store", "payload": payload }));
+        assert_eq!(
+            raw,
+            r#"{"action": "store", "payload": {"a": "caff\u00e8 \u2713 \ud83d\ude00", "b": [1, 2.5, null, true], "z": {"x": "q\"\n", "y": 1}}}"#
+        );
+        let data_hash = sha256_hex(&raw);
+        assert_eq!(data_hash, "7b3fb552760bdce37d9609dbfe8c35376c00deef6c685fa315a988cd1bfd0c74");
+        assert_eq!(
+            chain_hash(GENESIS_HASH, &data_hash),
+            "ce6b3798d7dfc0360031fd988cb8812b195ef641ccb5f892a2571cfd1f78a84e"
+        );
+    }
+
+    #[test]
+    fn appends_chain_and_survives_reopen() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit/ledger.jsonl");
+
+        let first = Ledger::new(&path).append("store", &json!({ "id": 1 })).unwrap();
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+
+        // A fresh instance must continue from the last record on disk.
+        let ledger = Ledger::new(&path);
+        let second = ledger.append("store", &json!({ "id": 2 })).unwrap();
+        assert_eq!(second.prev_hash, first.chain_hash);
+
+        let report = ledger.verify().unwrap();
+        assert_eq!(report, LedgerReport { records: 2, head: second.chain_hash });
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let ledger = Ledger::new(&path);
+        ledger.append("store", &json!({ "id": 1 })).unwrap();
+        ledger.append("store", &json!({ "id": 2 })).unwrap();
+
+        let tampered = fs::read_to_string(&path)
+            .unwrap()
+            .replacen("\"data_hash\":\"", "\"data_hash\":\"f", 1);
+        fs::write(&path, tampered).unwrap();
+        let err = ledger.verify().unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{err}");
+    }
+
+    #[test]
+    fn missing_ledger_is_an_empty_valid_chain() {
+        let dir = TempDir::new().unwrap();
+        let report = Ledger::new(dir.path().join("absent.jsonl")).verify().unwrap();
+        assert_eq!(report.records, 0);
+        assert_eq!(report.head, GENESIS_HASH);
+    }
+}
@@ -0,0 +1,500 @@
+//! MCP client for the local tool servers in `mcp_servers`, such as the TypeScript vault
+//! server. The online reasoner offers their tools to the model; every call runs here on
+//! the device, is chained into the audit ledger, and its result is redacted by the
+//! reasoner before it is sent back to the model.
+//!
+//! Servers are connected on first use. One that fails to start or initialize is logged
+//! and left out, so a missing tool server never blocks reasoning.
+
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, Command},
+    sync::{oneshot, OnceCell},
+};
+
+use crate::{ledger::Ledger, metrics::Metrics};
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+/// Covers server start-up (`initialize`) as well as each tool call.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// One `mcp_servers` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerSpec {
+    pub name: String,
+    pub endpoint: Endpoint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    /// Spawned child speaking newline-delimited JSON-RPC on stdin/stdout.
+    Stdio { program: String, args: Vec<String> },
+    /// Streamable HTTP endpoint URL.
+    Http(String),
+}
+
+impl ServerSpec {
+    /// Parse `name=http(s)://…` or `name=<program> [args…]`; arguments are split on
+    /// whitespace, without shell quoting.
+    pub fn parse(entry: &str) -> Result<Self> {
+        let (name, target) = entry.split_once('=').context("expected `name=<command or URL>`")?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            bail!("server name must be non-empty and use only letters, digits, `_` and `-`");
+        }
+        let target = target.trim();
+        let endpoint = if target.starts_with("http://") || target.starts_with("https://") {
+            Endpoint::Http(target.to_string())
+        } else {
+            let mut words = target.split_whitespace().map(String::from);
+            let program = words.next().context("missing command")?;
+            Endpoint::Stdio { program, args: words.collect() }
+        };
+        Ok(Self { name: name.to_string(), endpoint })
+    }
+}
+
+/// A tool advertised by a server.
+#[derive(Debug, Clone)]
+pub struct RemoteTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+enum Transport {
+    Stdio {
+        stdin: tokio::sync::Mutex<ChildStdin>,
+        pending: Pending,
+        /// Killed when the client is dropped.
+        _child: Child,
+    },
+    Http {
+        client: Client,
+        url: String,
+        session: Mutex<Option<String>>,
+    },
+}
+
+struct McpClient {
+    server: String,
+    transport: Transport,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    async fn connect(spec: &ServerSpec, http: &Client) -> Result<(Self, Vec<RemoteTool>)> {
+        let transport = match &spec.endpoint {
+            Endpoint::Stdio { program, args } => spawn(program, args)?,
+            Endpoint::Http(url) => Transport::Http {
+                client: http.clone(),
+                url: url.clone(),
+                session: Mutex::new(None),
+            },
+        };
+        let client = Self {
+            server: spec.name.clone(),
+            transport,
+            next_id: AtomicU64::new(1),
+        };
+
+        client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "counsel-ai-mcp-gateway", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        client.notify("notifications/initialized").await?;
+
+        let mut tools = Vec::new();
+        let mut cursor = Value::Null;
+        loop {
+            let params = if cursor.is_null() { json!({}) } else { json!({ "cursor": cursor }) };
+            let page = client.request("tools/list", params).await?;
+            for tool in page["tools"].as_array().into_iter().flatten() {
+                let Some(name) = tool["name"].as_str() else { continue };
+                tools.push(RemoteTool {
+                    name: name.to_string(),
+                    description: tool["description"].as_str().unwrap_or_default().to_string(),
+                    input_schema: tool
+                        .get("inputSchema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object" })),
+                });
+            }
+            cursor = page["nextCursor"].clone();
+            if !cursor.is_string() {
+                break;
+            }
+        }
+        Ok((client, tools))
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let reply = tokio::time::timeout(REQUEST_TIMEOUT, self.exchange(id, message))
+            .await
+            .map_err(|_| anyhow!("{}: `{method}` timed out", self.server))??;
+        if let Some(error) = reply.get("error") {
+            bail!("{}: `{method}` failed: {}", self.server, error["message"].as_str().unwrap_or_default());
+        }
+        Ok(reply["result"].clone())
+    }
+
+    async fn notify(&self, method: &str) -> Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match &self.transport {
+            Transport::Stdio { stdin, .. } => write_line(stdin, &message).await,
+            Transport::Http { .. } => self.post(&message).await.map(drop),
+        }
+    }
+
+    /// Send a request and wait for the response with the same `id`.
+    async fn exchange(&self, id: u64, message: Value) -> Result<Value> {
+        match &self.transport {
+            Transport::Stdio { stdin, pending, .. } => {
+                let (tx, rx) = oneshot::channel();
+                pending.lock().unwrap_or_else(|e| e.into_inner()).insert(id, tx);
+                if let Err(e) = write_line(stdin, &message).await {
+                    pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+                    return Err(e);
+                }
+                rx.await.map_err(|_| anyhow!("{}: server exited", self.server))
+            }
+            Transport::Http { .. } => {
+                let body = self.post(&message).await?;
+                body.into_iter()
+                    .find(|reply| reply["id"] == json!(id))
+                    .ok_or_else(|| anyhow!("{}: no response to request {id}", self.server))
+            }
+        }
+    }
+
+    /// POST one message; returns the JSON-RPC messages in the reply, whether sent as
+    /// JSON or as a `text/event-stream`.
+    async fn post(&self, message: &Value) -> Result<Vec<Value>> {
+        let Transport::Http { client, url, session } = &self.transport else {
+            unreachable!("post is only used for HTTP servers");
+        };
+        let mut request = client
+            .post(url)
+            .header("accept", "application/json, text/event-stream")
+            .header("mcp-protocol-version", PROTOCOL_VERSION)
+            .json(message);
+        if let Some(id) = session.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+            request = request.header(SESSION_HEADER, id);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("{}: failed to contact {url}", self.server))?
+            .error_for_status()
+            .with_context(|| format!("{}: request rejected", self.server))?;
+
+        if let Some(id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *session.lock().unwrap_or_else(|e| e.into_inner()) = Some(id.to_string());
+        }
+        if response.status() == StatusCode::ACCEPTED {
+            return Ok(Vec::new());
+        }
+        let event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        let text = response.text().await?;
+        Ok(if event_stream {
+            sse_messages(&text)
+        } else {
+            vec![serde_json::from_str(&text).with_context(|| format!("{}: invalid JSON-RPC reply", self.server))?]
+        })
+    }
+}
+
+fn spawn(program: &str, args: &[String]) -> Result<Transport> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to start `{program}`"))?;
+    let stdin = child.stdin.take().context("child stdin unavailable")?;
+    let stdout = child.stdout.take().context("child stdout unavailable")?;
+
+    let pending: Pending = Arc::default();
+    let routes = Arc::clone(&pending);
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Ok(message) = serde_json::from_str::<Value>(&line) else { continue };
+            // Server notifications and requests are not used by tool calls.
+            let Some(id) = message["id"].as_u64().filter(|_| message.get("method").is_none()) else {
+                continue;
+            };
+            if let Some(tx) = routes.lock().unwrap_or_else(|e| e.into_inner()).remove(&id) {
+                let _ = tx.send(message);
+            }
+        }
+        // Dropping the senders fails every request still waiting.
+        routes.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    });
+
+    Ok(Transport::Stdio {
+        stdin: tokio::sync::Mutex::new(stdin),
+        pending,
+        _child: child,
+    })
+}
+
+async fn write_line(stdin: &tokio::sync::Mutex<ChildStdin>, message: &Value) -> Result<()> {
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(format!("{message}\n").as_bytes()).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+/// JSON payloads of the `data:` lines of each server-sent event.
+fn sse_messages(stream: &str) -> Vec<Value> {
+    stream
+        .split("\n\n")
+        .filter_map(|event| {
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            serde_json::from_str(&data.join("\n")).ok()
+        })
+        .collect()
+}
+
+/// `<server>__<tool>`, restricted to the characters and length function-calling APIs accept.
+fn qualified_name(server: &str, tool: &str) -> String {
+    format!("{server}__{tool}")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .take(64)
+        .collect()
+}
+
+/// The configured tool servers, connected on first use.
+pub struct ToolHub {
+    specs: Vec<ServerSpec>,
+    http: Client,
+    ledger: Arc<Ledger>,
+    metrics: Arc<Metrics>,
+    servers: OnceCell<Vec<(McpClient, Vec<RemoteTool>)>>,
+}
+
+impl ToolHub {
+    pub fn new(specs: Vec<ServerSpec>, http: Client, ledger: Arc<Ledger>, metrics: Arc<Metrics>) -> Self {
+        Self {
+            specs,
+            http,
+            ledger,
+            metrics,
+            servers: OnceCell::new(),
+        }
+    }
+
+    async fn servers(&self) -> &[(McpClient, Vec<RemoteTool>)] {
+        self.servers
+            .get_or_init(|| async {
+                let mut servers = Vec::new();
+                for spec in &self.specs {
+                    match McpClient::connect(spec, &self.http).await {
+                        Ok((client, tools)) => {
+                            tracing::info!(server = %spec.name, tools = tools.len(), "Connected to MCP tool server");
+                            servers.push((client, tools));
+                        }
+                        Err(e) => tracing::warn!(server = %spec.name, "MCP tool server unavailable: {e:#}"),
+                    }
+                }
+                servers
+            })
+            .await
+    }
+
+    /// Every available tool as an OpenAI-style function definition.
+    pub async fn function_definitions(&self) -> Vec<Value> {
+        if self.specs.is_empty() {
+            return Vec::new();
+        }
+        self.servers()
+            .await
+            .iter()
+            .flat_map(|(client, tools)| {
+                tools.iter().map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": qualified_name(&client.server, &tool.name),
+                            "description": tool.description,
+                            "parameters": tool.input_schema,
+                        }
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Run the tool behind a qualified name and return its text output. Failures are
+    /// returned as text too, so the model can recover; a result is only released once
+    /// the call is recorded in the audit ledger.
+    pub async fn call(&self, qualified: &str, arguments: Value) -> String {
+        let Some((client, tool)) = self.servers().await.iter().find_map(|(client, tools)| {
+            tools
+                .iter()
+                .find(|tool| qualified_name(&client.server, &tool.name) == qualified)
+                .map(|tool| (client, tool))
+        }) else {
+            return format!("Tool error: unknown tool `{qualified}`");
+        };
+
+        let outcome = client
+            .request("tools/call", json!({ "name": tool.name, "arguments": arguments }))
+            .await;
+        let (ok, output) = match &outcome {
+            Ok(result) => (result["isError"] != json!(true), tool_text(result)),
+            Err(e) => (false, format!("{e:#}")),
+        };
+        self.metrics.record_tool_call(&client.server, ok);
+
+        let record = json!({
+            "server": client.server,
+            "tool": tool.name,
+            "arguments": arguments,
+            "is_error": !ok,
+            "output": output,
+        });
+        let ledger = Arc::clone(&self.ledger);
+        let audited = tokio::task::spawn_blocking(move || ledger.append("mcp_tool_call", &record))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
+        if let Err(e) = audited {
+            self.metrics.record_ledger_append_failure();
+            tracing::error!(server = %client.server, tool = %tool.name, "Tool call not audited: {e:#}");
+            return "Tool error: the call could not be audited, so its result is withheld".to_string();
+        }
+
+        if ok {
+            output
+        } else {
+            format!("Tool error: {output}")
+        }
+    }
+}
+
+/// The text content of a `tools/call` result, falling back to its structured content.
+fn tool_text(result: &Value) -> String {
+    let text: Vec<&str> = result["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|item| item["type"] == "text")
+        .filter_map(|item| item["text"].as_str())
+        .collect();
+    if text.is_empty() {
+        result.get("structuredContent").map(Value::to_string).unwrap_or_default()
+    } else {
+        text.join("\n")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A stdio server with one tool, `vault.get`, answering the fixed request sequence
+    /// initialize (1), tools/list (2) and one tools/call (3).
+    pub(crate) fn fake_vault_server() -> ServerSpec {
+        let script = r#"
+            read -r _; echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"vault","version":"0"}}}'
+            read -r _
+            read -r _; echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"vault.get","description":"Get a record","inputSchema":{"type":"object","properties":{"id":{"type":"string"}}}}]}}'
+            read -r _; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"Client: mario.rossi@example.com"}]}}'
+            read -r _
+        "#;
+        ServerSpec {
+            name: "vault".to_string(),
+            endpoint: Endpoint::Stdio {
+                program: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+            },
+        }
+    }
+
+    #[test]
+    fn parses_server_entries() {
+        assert_eq!(
+            ServerSpec::parse("vault=node dist/index.js --stdio").unwrap(),
+            ServerSpec {
+                name: "vault".into(),
+                endpoint: Endpoint::Stdio {
+                    program: "node".into(),
+                    args: vec!["dist/index.js".into(), "--stdio".into()],
+                },
+            }
+        );
+        assert_eq!(
+            ServerSpec::parse("search = http://127.0.0.1:7000/mcp").unwrap().endpoint,
+            Endpoint::Http("http://127.0.0.1:7000/mcp".into())
+        );
+        assert!(ServerSpec::parse("node dist/index.js").is_err());
+        assert!(ServerSpec::parse("my vault=node").is_err());
+        assert!(ServerSpec::parse("vault=").is_err());
+
+        assert_eq!(qualified_name("vault", "vault.get"), "vault__vault_get");
+        assert_eq!(
+            sse_messages("event: message\ndata: {\"id\":1}\n\ndata: {\"id\":2}\n\n"),
+            vec![json!({ "id": 1 }), json!({ "id": 2 })]
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_are_run_locally_and_audited() {
+        let dir = TempDir::new().unwrap();
+        let ledger = Arc::new(Ledger::new(dir.path().join("ledger.jsonl")));
+        let metrics = Arc::new(Metrics::new());
+        let broken = ServerSpec::parse("broken=/nonexistent/mcp-server").unwrap();
+        let hub = ToolHub::new(vec![broken, fake_vault_server()], Client::new(), Arc::clone(&ledger), Arc::clone(&metrics));
+
+        let functions = hub.function_definitions().await;
+        assert_eq!(functions.len(), 1, "the broken server is skipped");
+        assert_eq!(functions[0]["function"]["name"], "vault__vault_get");
+
+        let output = hub.call("vault__vault_get", json!({ "id": "c-1" })).await;
+        assert_eq!(output, "Client: mario.rossi@example.com");
+        assert_eq!(ledger.verify().unwrap().records, 1);
+        assert!(metrics
+            .render()
+            .contains(r#"counsel_ai_mcp_tool_calls_total{outcome="success",server="vault"} 1"#));
+
+        assert!(hub.call("vault__missing", json!({})).await.starts_with("Tool error"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Prometheus metrics for the gateway.
+//! Request traffic is recorded by the [`track_requests`] middleware; reasoning,
+//! redaction and storage paths record their own counters on the [`Metrics`] held in `AppState`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+/// Latency buckets in seconds; local inference can take minutes.
+const DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Which reasoner served a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Online,
+    Local,
+}
+
+impl Backend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Backend::Online => "online",
+            Backend::Local => "local",
+        }
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    started: Instant,
+    uptime: IntGauge,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    reasoner_requests: IntCounterVec,
+    reasoner_tokens: IntCounterVec,
+    local_queue_depth: IntGauge,
+    redactions: IntCounterVec,
+    ledger_append_failures: IntCounter,
+    tool_calls: IntCounterVec,
+    injection_detections: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        // Metric definitions are static, so registration can only fail on a programming error.
+        fn register<T: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: T) -> T {
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("metric names are unique");
+            metric
+        }
+
+        Self {
+            started: Instant::now(),
+            uptime: register(
+                &registry,
+                IntGauge::new("counsel_ai_uptime_seconds", "Seconds since the gateway started").unwrap(),
+            ),
+            requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("counsel_ai_requests_total", "HTTP requests by route and status"),
+                    &["method", "route", "status"],
+                )
+                .unwrap(),
+            ),
+            request_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "counsel_ai_request_duration_seconds",
+                        "HTTP request latency by route and status",
+                    )
+                    .buckets(DURATION_BUCKETS.to_vec()),
+                    &["method", "route", "status"],
+                )
+                .unwrap(),
+            ),
+            reasoner_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("counsel_ai_reasoner_requests_total", "Reasoning calls by backend and outcome"),
+                    &["backend", "outcome"],
+                )
+                .unwrap(),
+            ),
+            reasoner_tokens: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("counsel_ai_reasoner_tokens_total", "Tokens reported by the reasoner backend"),
+                    &["backend", "kind"],
+                )
+                .unwrap(),
+            ),
+            local_queue_depth: register(
+                &registry,
+                IntGauge::new(
+                    "counsel_ai_local_inference_queue_depth",
+                    "Local inference requests waiting or running",
+                )
+                .unwrap(),
+            ),
+            redactions: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("counsel_ai_redactions_total", "Identifying values redacted before leaving the device"),
+                    &["kind"],
+                )
+                .unwrap(),
+            ),
+            ledger_append_failures: register(
+                &registry,
+                IntCounter::new(
+                    "counsel_ai_ledger_append_failures_total",
+                    "Audit ledger entries that could not be written",
+                )
+                .unwrap(),
+            ),
+            tool_calls: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("counsel_ai_mcp_tool_calls_total", "Calls to external MCP tool servers by server and outcome"),
+                    &["server", "outcome"],
+                )
+                .unwrap(),
+            ),
+            injection_detections: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "counsel_ai_injection_detections_total",
+                        "Context segments matching a prompt-injection rule, by rule and action",
+                    ),
+                    &["rule", "action"],
+                )
+                .unwrap(),
+            ),
+            registry,
+        }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: StatusCode, seconds: f64) {
+        let status = status.as_u16().to_string();
+        let labels = [method, route, status.as_str()];
+        self.requests.with_label_values(&labels).inc();
+        self.request_duration.with_label_values(&labels).observe(seconds);
+    }
+
+    pub fn record_reasoner(&self, backend: Backend, success: bool) {
+        let outcome = if success { "success" } else { "error" };
+        self.reasoner_requests
+            .with_label_values(&[backend.as_str(), outcome])
+            .inc();
+    }
+
+    pub fn record_tokens(&self, backend: Backend, prompt: u64, completion: u64) {
+        self.reasoner_tokens
+            .with_label_values(&[backend.as_str(), "prompt"])
+            .inc_by(prompt);
+        self.reasoner_tokens
+            .with_label_values(&[backend.as_str(), "completion"])
+            .inc_by(completion);
+    }
+
+    /// Count a local inference request for as long as the returned guard lives.
+    pub fn enqueue_local(&self) -> QueueGuard<'_> {
+        self.local_queue_depth.inc();
+        QueueGuard(&self.local_queue_depth)
+    }
+
+    pub fn record_redactions(&self, kind: &str, count: u64) {
+        self.redactions.with_label_values(&[kind]).inc_by(count);
+    }
+
+    pub fn record_ledger_append_failure(&self) {
+        self.ledger_append_failures.inc();
+    }
+
+    pub fn record_tool_call(&self, server: &str, success: bool) {
+        let outcome = if success { "success" } else { "error" };
+        self.tool_calls.with_label_values(&[server, outcome]).inc();
+    }
+
+    pub fn record_injection(&self, rule: &str, action: &str) {
+        self.injection_detections.with_label_values(&[rule, action]).inc();
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        self.uptime.set(self.uptime().as_secs() as i64);
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding into a Vec cannot fail");
+        String::from_utf8(buffer).expect("exposition format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decrements the local queue depth when dropped.
+pub struct QueueGuard<'a>(&'a IntGauge);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Records a request count and latency for every response, labelled with the
+/// matched route template rather than the raw path to keep cardinality bounded.
+pub async fn track_requests(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    let response = next.run(req).await;
+    metrics.observe_request(&method, &route, response.status(), started.elapsed().as_secs_f64());
+    response
+}
+
+/// `GET /metrics` body with the exposition content type.
+pub fn exposition(metrics: &Metrics) -> Response {
+    (
+        [(CONTENT_TYPE, TextEncoder::new().format_type().to_owned())],
+        metrics.render(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn sample_value(text: &str, series: &str) -> Option<f64> {
+        text.lines()
+            .find_map(|line| line.strip_prefix(series)?.trim().parse().ok())
+    }
+
+    #[tokio::test]
+    async fn middleware_labels_requests_by_route_template_and_status() {
+        let metrics = Arc::new(Metrics::new());
+        let app = Router::new()
+            .route("/items/:id", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(Arc::clone(&metrics), track_requests));
+
+        for path in ["/items/1", "/items/2"] {
+            let res = app
+                .clone()
+                .oneshot(Request::get(path).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let text = metrics.render();
+        let count = sample_value(
+            &text,
+            r#"counsel_ai_requests_total{method="GET",route="/items/:id",status="200"}"#,
+        );
+        assert_eq!(count, Some(2.0), "{text}");
+        assert!(text.contains(r#"counsel_ai_request_duration_seconds_bucket{method="GET",route="/items/:id",status="200",le="0.01"}"#));
+        assert!(!text.contains("/items/1"));
+    }
+
+    #[test]
+    fn queue_guard_restores_depth() {
+        let metrics = Metrics::new();
+        {
+            let _a = metrics.enqueue_local();
+            let _b = metrics.enqueue_local();
+            assert_eq!(metrics.local_queue_depth.get(), 2);
+        }
+        assert_eq!(metrics.local_queue_depth.get(), 0);
+    }
+
+    #[test]
+    fn renders_valid_exposition_for_every_family() {
+        let metrics = Metrics::new();
+        metrics.record_reasoner(Backend::Online, true);
+        metrics.record_tokens(Backend::Online, 12, 30);
+        metrics.record_redactions("email", 2);
+        metrics.record_ledger_append_failure();
+
+        let text = metrics.render();
+        for family in [
+            "counsel_ai_uptime_seconds gauge",
+            "counsel_ai_reasoner_requests_total counter",
+            "counsel_ai_reasoner_tokens_total counter",
+            "counsel_ai_local_inference_queue_depth gauge",
+            "counsel_ai_redactions_total counter",
+            "counsel_ai_ledger_append_failures_total counter",
+        ] {
+            assert!(text.contains(&format!("# TYPE {family}")), "missing {family}");
+        }
+        assert_eq!(
+            sample_value(&text, r#"counsel_ai_reasoner_tokens_total{backend="online",kind="completion"}"#),
+            Some(30.0)
+        );
+        assert_eq!(sample_value(&text, r#"counsel_ai_redactions_total{kind="email"}"#), Some(2.0));
+    }
+}
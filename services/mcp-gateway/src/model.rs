@@ -1,20 +1,33 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
-use validator::{Validate, ValidationError};
-use std::collections::HashSet;
+use validator::Validate;
 use utoipa::ToSchema;
 
-/// --- Query and Reasoning Data Models ---
+use crate::{
+    citations::{self, CitationMatch},
+    signing::SignatureStatus,
+    verification::{CitationVerdict, Grounding},
+};
+
+// --- Query and Reasoning Data Models ---
 
 /// Incoming query from UI
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct QueryRequest {
     #[validate(length(min = 1, max = 10000, message = "Text must be between 1 and 10000 characters"))]
     pub text: String,
+    /// Matter whose workspace (`<storage_path>/matters/<matter>`) `files` are read from
+    #[serde(default)]
+    pub matter: Option<String>,
+    /// Paths relative to the matter workspace, loaded as context
     #[serde(default)]
     #[validate(length(max = 10, message = "Maximum 10 files allowed"))]
     pub files: Option<Vec<String>>,
+    /// Ids from `/v1/documents` whose extracted text is added as context
+    #[serde(default)]
+    #[validate(length(max = 10, message = "Maximum 10 documents allowed"))]
+    pub documents: Option<Vec<Uuid>>,
 }
 
 /// Request to GPT or local model
@@ -27,6 +40,20 @@ pub struct ReasoningRequest {
     #[serde(default)]
     #[validate(length(max = 20, message = "Maximum 20 context segments allowed"))]
     pub context: Vec<String>,
+    /// Context segments `/v1/query` cut to `context_document_bytes`; informational only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<TruncatedContext>,
+}
+
+/// A referenced document whose text did not fit the context budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TruncatedContext {
+    /// Position in `context`, counting from 1 like the untrusted-document fences
+    pub segment: usize,
+    /// Length of the normalized document text, in bytes
+    pub original_bytes: usize,
+    /// Structural sections (articles, clauses, motivi) left out
+    pub omitted_sections: usize,
 }
 
 /// Unified reasoning response structure
@@ -35,10 +62,74 @@ pub struct ReasoningResponse {
     /// Summarized or reasoned output text
     #[validate(length(max = 100000, message = "Summary too long"))]
     pub summary: String,
-    /// Optional cited references or sources
-    #[serde(default)]
+    /// Legal references cited in `summary`, parsed and normalized; plain strings, the form
+    /// used before citations were parsed, are accepted on input and parsed on the way in
+    #[serde(default, deserialize_with = "parsed_or_plain_citations")]
     #[validate(length(max = 100, message = "Maximum 100 citations allowed"))]
-    pub citations: Vec<String>,
+    pub citations: Vec<CitationMatch>,
+    /// Gateway signature, present on every response the gateway produced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
+}
+
+impl ReasoningResponse {
+    /// An unsigned response for `summary` with the citations it contains (the first 100).
+    pub fn new(summary: String) -> Self {
+        let mut citations = citations::extract(&summary);
+        citations.truncate(100);
+        Self { summary, citations, signature: None }
+    }
+}
+
+/// `citations` as objects or, from clients predating parsed citations, as strings.
+fn parsed_or_plain_citations<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<CitationMatch>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Parsed(CitationMatch),
+        Plain(String),
+    }
+
+    Ok(Vec::<Entry>::deserialize(deserializer)?
+        .into_iter()
+        .flat_map(|entry| match entry {
+            Entry::Parsed(citation) => vec![citation],
+            Entry::Plain(text) => citations::extract(&text),
+        })
+        .collect())
+}
+
+/// Ed25519 signature binding a response to its request and the model that produced it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResponseSignature {
+    /// Always `ed25519`
+    pub algorithm: String,
+    /// First 16 hex digits of the SHA-256 of the gateway's public key
+    pub key_id: String,
+    /// SHA-256 of the `ReasoningRequest` JSON the response was reasoned from
+    pub request_sha256: String,
+    /// Online model name or local model file
+    pub model: String,
+    /// SHA-256 of the local model file per the trusted registry; absent for online models
+    #[serde(default)]
+    pub model_sha256: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schema(value_type = String, format = DateTime)]
+    pub signed_at: OffsetDateTime,
+    /// Hex signature over the response and the fields above
+    pub signature: String,
+}
+
+/// Body of `/verify`: a reasoning response and, optionally, the context it was reasoned from
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct VerifyRequest {
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub response: ReasoningResponse,
+    /// The `ReasoningRequest.context` behind the response; enables the grounding check
+    #[serde(default)]
+    #[validate(length(max = 20, message = "Maximum 20 context segments allowed"))]
+    pub context: Vec<String>,
 }
 
 /// Verification result for model integrity
@@ -46,10 +137,51 @@ pub struct ReasoningResponse {
 pub struct VerifyResponse {
     pub valid: bool,
     pub reason: String,
+    /// Verdict for each citation found in `summary`, checked against the knowledge base
+    pub citations: Vec<CitationVerdict>,
+    /// Whether the response carries this gateway's valid signature
+    pub signature: SignatureStatus,
+    /// Support of each summary sentence by `context`; absent when no context was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grounding: Option<Grounding>,
+}
+
+/// Result of `/store`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StoreResponse {
+    pub status: String,
+    pub id: Uuid,
+    /// Chain hash of the audit ledger record for this entry
+    pub chain_hash: String,
+}
+
+/// Multipart body of `/v1/documents`; only describes the form in the OpenAPI spec
+#[derive(ToSchema)]
+pub struct DocumentUpload {
+    /// PDF, DOCX or UTF-8 text file
+    #[schema(value_type = String, format = Binary)]
+    #[allow(dead_code)]
+    pub file: Vec<u8>,
+}
+
+/// Result of `/v1/documents`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DocumentResponse {
+    /// Reference this id in `QueryRequest.documents`
+    pub id: Uuid,
+    pub filename: String,
+    pub media_type: String,
+    /// SHA-256 of the uploaded bytes, committed to the audit ledger
+    pub sha256: String,
+    pub size: usize,
+    /// Characters of extracted text
+    pub characters: usize,
+    /// Chain hash of the audit ledger record for this upload
+    pub chain_hash: String,
 }
 
 /// Log entry for stored operations (auditable)
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LogEntry {
     pub id: Uuid,
     pub timestamp: OffsetDateTime,
@@ -57,67 +189,128 @@ pub struct LogEntry {
     pub payload: serde_json::Value,
 }
 
-impl Default for ReasoningResponse {
-    fn default() -> Self {
-        Self {
-            summary: String::new(),
-            citations: vec![],
-        }
-    }
+// --- Knowledge Base and Store Lookups ---
+
+/// Full-text search of the legal knowledge base
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct KbSearchRequest {
+    #[validate(length(min = 1, max = 1000, message = "Query must be between 1 and 1000 characters"))]
+    pub query: String,
+    /// Maximum number of passages to return (default 5)
+    #[serde(default)]
+    #[validate(range(min = 1, max = 50, message = "Limit must be between 1 and 50"))]
+    pub limit: Option<usize>,
+}
+
+/// A knowledge base passage
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KbHit {
+    pub id: String,
+    pub text: String,
+    /// Where the passage comes from (statute, judgment, doctrine)
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KbSearchResponse {
+    pub hits: Vec<KbHit>,
+}
+
+/// Lookup of a stored log entry
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StoreGetRequest {
+    pub id: Uuid,
+}
+
+/// A payload to store and chain into the audit ledger
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StorePutRequest {
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
 }
 
 /// Input sanitization and validation utilities
 pub mod validation {
     use super::*;
+    use crate::error::ApiError;
     use regex::Regex;
-    use std::collections::HashSet;
-
-    /// Sanitize text input by removing potentially dangerous content
-    pub fn sanitize_text(input: &str) -> String {
-        // Remove control characters except newlines and tabs
-        let cleaned = input
-            .chars()
-            .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
-            .collect::<String>();
-        
-        // Remove excessive whitespace
-        let re = Regex::new(r"\s+").unwrap();
-        re.replace_all(&cleaned, " ").trim().to_string()
+    use std::sync::OnceLock;
+    use unicode_normalization::UnicodeNormalization;
+
+    /// Normalize text without flattening its structure: Unicode NFC; control, bidi-override
+    /// and zero-width characters removed; curly quotes and guillemets made straight; `§ 2`
+    /// spacing; single spaces within a line. Line breaks are kept, with at most one blank
+    /// line between paragraphs, so commi, lists and clauses reach the model intact
+    pub fn normalize_text(input: &str) -> String {
+        let mut cleaned = String::with_capacity(input.len());
+        let mut chars = input.nfc().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' | '\u{0C}' | '\u{85}' | '\u{2028}' => cleaned.push('\n'),
+                '\u{2029}' => cleaned.push_str("\n\n"),
+                '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => cleaned.push('\''),
+                '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' | '«' | '»' => cleaned.push('"'),
+                c if is_invisible(c) => {}
+                c if c.is_whitespace() => cleaned.push(' '),
+                c if c.is_control() => {}
+                c => cleaned.push(c),
+            }
+        }
+
+        let mut text = String::with_capacity(cleaned.len());
+        let mut blank_lines = 0;
+        for line in cleaned.split('\n') {
+            let line = line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ");
+            if line.is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            blank_lines = 0;
+            text.push_str(&line);
+        }
+        section_sign().replace_all(&text, "$1 $2").into_owned()
+    }
+
+    /// Invisible characters that can reorder or hide text: bidi embeddings, overrides,
+    /// isolates and marks, zero-width characters, the BOM and soft hyphens
+    fn is_invisible(c: char) -> bool {
+        matches!(
+            c,
+            '\u{202A}'..='\u{202E}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{061C}'
+                | '\u{2060}'
+                | '\u{FEFF}'
+                | '\u{00AD}'
+        )
+    }
+
+    fn section_sign() -> &'static Regex {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        PATTERN.get_or_init(|| Regex::new(r"(§§?) ?(\d)").unwrap())
     }
 
-    /// Validate file paths to prevent directory traversal
-    pub fn validate_file_paths(paths: &[String]) -> Result<(), ValidationError> {
+    /// Reject obviously unsafe file paths early; the matter workspace enforces containment
+    /// after resolving symlinks
+    pub fn validate_file_paths(paths: &[String]) -> Result<(), ApiError> {
         for path in paths {
             if path.contains("..") || path.starts_with('/') || path.contains('\\') {
-                return Err(ValidationError::new("Invalid file path"));
+                return Err(ApiError::validation(format!("Invalid file path: {path}")));
             }
         }
         Ok(())
     }
 
-    /// Check for potentially malicious content
-    pub fn check_malicious_content(input: &str) -> bool {
-        let dangerous_patterns = [
-            r"<script",
-            r"javascript:",
-            r"data:",
-            r"vbscript:",
-            r"onload\s*=",
-            r"onerror\s*=",
-        ];
-        
-        let re = Regex::new(&dangerous_patterns.join("|")).unwrap();
-        re.is_match(input)
-    }
-
     /// Validate and sanitize query request
-    pub fn validate_query_request(req: &mut QueryRequest) -> Result<(), ValidationError> {
-        // Sanitize text
-        req.text = sanitize_text(&req.text);
-        
-        // Check for malicious content
-        if check_malicious_content(&req.text) {
-            return Err(ValidationError::new("Potentially malicious content detected"));
+    pub fn validate_query_request(req: &mut QueryRequest) -> Result<(), ApiError> {
+        req.text = normalize_text(&req.text);
+        if req.text.is_empty() {
+            return Err(ApiError::validation("Text is empty after normalization"));
         }
         
         // Validate file paths if present
@@ -128,22 +321,53 @@ pub mod validation {
         Ok(())
     }
 
-    /// Validate reasoning request
-    pub fn validate_reasoning_request(req: &mut ReasoningRequest) -> Result<(), ValidationError> {
-        // Sanitize prompt
-        req.prompt = sanitize_text(&req.prompt);
-        
-        // Check for malicious content
-        if check_malicious_content(&req.prompt) {
-            return Err(ValidationError::new("Potentially malicious content detected"));
+    /// Validate reasoning request; injection checks are the content policy's
+    pub fn validate_reasoning_request(req: &mut ReasoningRequest) -> Result<(), ApiError> {
+        req.prompt = normalize_text(&req.prompt);
+        if req.prompt.is_empty() {
+            return Err(ApiError::validation("Prompt is empty after normalization"));
         }
         
-        // Sanitize context
         req.context = req.context
             .iter()
-            .map(|c| sanitize_text(c))
+            .map(|c| normalize_text(c))
             .collect();
         
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{validation::normalize_text, ReasoningResponse};
+
+    #[test]
+    fn normalization_keeps_legal_structure() {
+        let text = "Art.\u{00A0}1453\r\n1. Nei contratti  con prestazioni corrispettive\u{200B}\r\n\r\n\r\n\
+                    2. La risoluzione può essere domandata\t anche\u{202E} quando\n  a) il contratto \u{201C}è\u{201D} l\u{2019}atto";
+        assert_eq!(
+            normalize_text(text),
+            "Art. 1453\n1. Nei contratti con prestazioni corrispettive\n\n\
+             2. La risoluzione può essere domandata anche quando\na) il contratto \"è\" l'atto"
+        );
+        // Decomposed accents are composed; section signs get one space.
+        assert_eq!(normalize_text("perche\u{0301} ex §2 e §§  3-4 «testo»"), "perché ex § 2 e §§ 3-4 \"testo\"");
+        assert_eq!(normalize_text("\u{2066}\u{0007} \n"), "");
+    }
+
+    #[test]
+    fn citations_are_read_as_objects_or_strings() {
+        let response = ReasoningResponse::new("Viola l'art. 1218 c.c.".into());
+        let round_trip: ReasoningResponse =
+            serde_json::from_value(serde_json::to_value(&response).unwrap()).unwrap();
+        assert_eq!(round_trip.citations, response.citations);
+
+        let plain: ReasoningResponse = serde_json::from_value(serde_json::json!({
+            "summary": "Viola l'art. 1218 c.c.",
+            "citations": ["art. 1218 c.c.", "not a citation"],
+        }))
+        .unwrap();
+        assert_eq!(plain.citations.len(), 1);
+        assert_eq!(plain.citations[0].canonical, response.citations[0].canonical);
+    }
+}
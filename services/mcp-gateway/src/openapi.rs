@@ -1,35 +1,76 @@
-use utoipa::OpenApi;
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Server,
+    },
+    Modify, OpenApi,
+};
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
+    citations::{Act, Citation, CitationMatch, Code, Court, Division},
+    config::Config,
+    error::Problem,
+    health::{self, *},
+    mcp,
     model::*,
-    routes::*,
-    health::*,
+    routes,
+    signing::SignatureStatus,
+    verification::{CitationVerdict, Grounding, UnsupportedStatement, Verdict},
+    version::{self, BuildInfo, VersionResponse},
 };
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        health::health_check,
+        health::livez,
+        health::readyz,
+        health::startupz,
+        health::metrics,
         routes::query,
         routes::reason,
         routes::reason_local,
         routes::verify,
         routes::store,
-        health::health_check,
-        health::metrics,
+        routes::upload_document,
+        version::version,
+        mcp::http,
     ),
     components(
         schemas(
             QueryRequest,
             ReasoningRequest,
+            TruncatedContext,
             ReasoningResponse,
+            ResponseSignature,
+            SignatureStatus,
+            CitationMatch,
+            Citation,
+            Code,
+            Act,
+            Court,
+            Division,
+            VerifyRequest,
             VerifyResponse,
+            CitationVerdict,
+            Verdict,
+            Grounding,
+            UnsupportedStatement,
+            StoreResponse,
+            DocumentUpload,
+            DocumentResponse,
             LogEntry,
             HealthResponse,
-            HealthChecks,
+            ProbeStatus,
+            ProbeResponse,
             CheckResult,
+            Problem,
+            VersionResponse,
+            BuildInfo,
         )
     ),
+    modifiers(&BearerAuth),
     tags(
         (name = "counsel-ai", description = "Counsel AI Legal Assistant API")
     ),
@@ -44,15 +85,41 @@ use crate::{
         license(
             name = "Proprietary"
         )
-    ),
-    servers(
-        (url = "http://localhost:5142", description = "Development server"),
-        (url = "https://api.counsel-ai.com", description = "Production server")
     )
 )]
 pub struct ApiDoc;
 
-pub fn create_swagger_ui() -> SwaggerUi {
+/// `bearer_auth`: the gateway `api_key`, sent as `Authorization: Bearer <key>`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.components.get_or_insert_with(Default::default).add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("The gateway API key (`api_key` / `API_KEY`)"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// The spec with servers from `openapi_servers`, or the local bind port when none are configured.
+pub fn spec(config: &Config) -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    let servers = if config.openapi_servers.is_empty() {
+        let port = config.bind_addr.rsplit(':').next().unwrap_or("5142");
+        vec![Server::new(format!("http://localhost:{port}"))]
+    } else {
+        config.openapi_servers.iter().map(Server::new).collect()
+    };
+    doc.servers = Some(servers);
+    doc
+}
+
+pub fn create_swagger_ui(config: &Config) -> SwaggerUi {
     SwaggerUi::new("/docs")
-        .url("/api-docs/openapi.json", ApiDoc::openapi())
-}
\ No newline at end of file
+        .url("/api-docs/openapi.json", spec(config))
+}
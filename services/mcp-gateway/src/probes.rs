@@ -0,0 +1,354 @@
+//! Dependency probes reported by `/health`.
+//! Each probe checks one upstream or local resource; [`crate::health::HealthRegistry`]
+//! runs them concurrently, applies a timeout and caches results.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, Context, Result};
+use pgp::{composed::SignedPublicKey, Deserializable, StandaloneSignature};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+use crate::{
+    config::LiveConfig,
+    health::{HealthProbe, ProbeFuture, ProbeOutcome},
+    ledger::Ledger,
+};
+
+/// Qdrant's own liveness endpoint at `vector_db_url`.
+pub struct QdrantProbe {
+    pub client: Client,
+    pub url: String,
+}
+
+impl HealthProbe for QdrantProbe {
+    fn name(&self) -> &'static str {
+        "vector_db"
+    }
+
+    fn check(&self) -> ProbeFuture<'_> {
+        Box::pin(async move {
+            let url = format!("{}/healthz", self.url.trim_end_matches('/'));
+            match self.client.get(&url).send().await {
+                Ok(res) if res.status().is_success() => ProbeOutcome::healthy("Qdrant reachable"),
+                Ok(res) => ProbeOutcome::degraded(format!("Qdrant returned HTTP {}", res.status().as_u16())),
+                Err(e) => ProbeOutcome::degraded(format!("Qdrant unreachable: {e}")),
+            }
+        })
+    }
+}
+
+/// Lists models on the online reasoner, which exercises both reachability and the API key.
+pub struct ReasonerProbe {
+    pub client: Client,
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+impl HealthProbe for ReasonerProbe {
+    fn name(&self) -> &'static str {
+        "online_reasoner"
+    }
+
+    fn check(&self) -> ProbeFuture<'_> {
+        Box::pin(async move {
+            let Some(api_key) = &self.api_key else {
+                return ProbeOutcome::healthy("Online reasoner not configured; local only");
+            };
+            let url = format!("{}/models", self.base_url.trim_end_matches('/'));
+            match self.client.get(&url).bearer_auth(api_key).send().await {
+                Ok(res) if res.status().is_success() => ProbeOutcome::healthy("Reasoner models endpoint reachable"),
+                Ok(res) if matches!(res.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                    ProbeOutcome::degraded("Reasoner rejected the configured API key")
+                }
+                Ok(res) => ProbeOutcome::degraded(format!("Reasoner returned HTTP {}", res.status().as_u16())),
+                Err(e) => ProbeOutcome::degraded(format!("Reasoner unreachable: {e}")),
+            }
+        })
+    }
+}
+
+/// Presence of the local model and its verification state, as recorded in the
+/// `.verification.json` cache that model-manager and the desktop app write next to it.
+/// `trusted_models.json` is read from `registry_dir`, defaulting to the model's directory,
+/// and only trusted when its signature verifies.
+pub struct LocalModelProbe {
+    /// Read per check: the active local model can change on reload.
+    pub config: LiveConfig,
+}
+
+const VERIFICATION_CACHE_FILE: &str = ".verification.json";
+const REGISTRY_FILE: &str = "trusted_models.json";
+const SIGNATURE_FILE: &str = "trusted_models.json.asc";
+const PUBLIC_KEY_FILE: &str = "publickey.asc";
+
+/// The `.verification.json` format written by `VerificationCache` in `tauri/src/inventory.rs`.
+/// The gateway image is built from this directory alone, so the reading side is declared
+/// here; both crates test against `tests/fixtures/models/.verification.json`.
+#[derive(Deserialize)]
+struct VerificationCache {
+    entries: std::collections::BTreeMap<String, CachedDigest>,
+}
+
+#[derive(Deserialize)]
+struct CachedDigest {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    sha256: String,
+}
+
+#[derive(Deserialize)]
+struct Registry {
+    models: Vec<RegistryModel>,
+}
+
+#[derive(Deserialize)]
+struct RegistryModel {
+    file: String,
+    sha256: String,
+}
+
+impl LocalModelProbe {
+    fn inspect(&self) -> ProbeOutcome {
+        let config = self.config.get();
+        let Some(path) = config.local_model_path.as_deref().map(Path::new) else {
+            return ProbeOutcome::healthy("No local model configured");
+        };
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return ProbeOutcome::unhealthy(format!("{} is not a file", path.display())),
+            Err(e) => return ProbeOutcome::unhealthy(format!("Local model {}: {e}", path.display())),
+        };
+        let (Some(dir), Some(file)) = (path.parent(), path.file_name().and_then(|f| f.to_str())) else {
+            return ProbeOutcome::degraded("Local model path has no file name");
+        };
+
+        let registry_dir = config.registry_dir.as_deref().map(Path::new).unwrap_or(dir);
+        let registry = match verified_registry(registry_dir) {
+            Ok(registry) => registry,
+            Err(e) => return ProbeOutcome::degraded(format!("{file} unverified: trusted registry rejected ({e:#})")),
+        };
+        let Some(expected) = registry.sha256(file) else {
+            return ProbeOutcome::degraded(format!("{file} is not listed in the trusted registry"));
+        };
+        let modified = metadata.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok());
+        let cached = fs::read(dir.join(VERIFICATION_CACHE_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<VerificationCache>(&bytes).ok())
+            .and_then(|mut cache| cache.entries.remove(file))
+            .filter(|c| {
+                Some((c.len, c.modified_secs, c.modified_nanos))
+                    == modified.map(|m| (metadata.len(), m.as_secs(), m.subsec_nanos()))
+            });
+
+        match cached {
+            Some(c) if c.sha256.eq_ignore_ascii_case(&expected) => {
+                ProbeOutcome::healthy(format!("{file} present and verified"))
+            }
+            Some(_) => ProbeOutcome::unhealthy(format!("{file} does not match the trusted registry hash")),
+            None => ProbeOutcome::degraded(format!("{file} present but not verified since last change")),
+        }
+    }
+}
+
+impl Registry {
+    fn sha256(self, file: &str) -> Option<String> {
+        self.models.into_iter().find(|m| m.file == file).map(|m| m.sha256)
+    }
+}
+
+/// SHA-256 the trusted registry lists for the model at `path`; the registry is read from
+/// `registry_dir`, defaulting to the model's directory, and ignored unless it verifies.
+pub fn trusted_sha256(registry_dir: Option<&str>, path: &Path) -> Option<String> {
+    let dir = path.parent()?;
+    let file = path.file_name()?.to_str()?;
+    verified_registry(registry_dir.map(Path::new).unwrap_or(dir)).ok()?.sha256(file)
+}
+
+/// `trusted_models.json` in `dir`, parsed only after `trusted_models.json.asc` verifies
+/// against `publickey.asc` there, as `load_verified_registry` in the tauri crate does.
+fn verified_registry(dir: &Path) -> Result<Registry> {
+    let read = |name: &str| fs::read(dir.join(name)).with_context(|| format!("failed to read {name}"));
+    let registry = read(REGISTRY_FILE)?;
+    let (public_key, _) =
+        SignedPublicKey::from_armor_single(&read(PUBLIC_KEY_FILE)?[..]).map_err(|_| anyhow!("invalid public key format"))?;
+    let (signature, _) =
+        StandaloneSignature::from_armor_single(&read(SIGNATURE_FILE)?[..]).map_err(|_| anyhow!("invalid signature file"))?;
+    signature
+        .verify(&public_key, &registry)
+        .map_err(|_| anyhow!("signature verification failed"))?;
+    serde_json::from_slice(&registry).context("malformed trusted_models.json")
+}
+
+impl HealthProbe for LocalModelProbe {
+    fn name(&self) -> &'static str {
+        "local_model"
+    }
+
+    fn check(&self) -> ProbeFuture<'_> {
+        Box::pin(async move { self.inspect() })
+    }
+}
+
+/// Full hash-chain verification of the audit ledger.
+pub struct LedgerProbe {
+    pub ledger: Arc<Ledger>,
+}
+
+impl HealthProbe for LedgerProbe {
+    fn name(&self) -> &'static str {
+        "ledger"
+    }
+
+    fn critical(&self) -> bool {
+        true
+    }
+
+    fn check(&self) -> ProbeFuture<'_> {
+        Box::pin(async move {
+            let ledger = Arc::clone(&self.ledger);
+            match tokio::task::spawn_blocking(move || ledger.verify()).await {
+                Ok(Ok(report)) => ProbeOutcome::healthy(format!("{} records, chain intact", report.records)),
+                Ok(Err(e)) => ProbeOutcome::unhealthy(format!("Ledger integrity check failed: {e:#}")),
+                Err(e) => ProbeOutcome::unhealthy(format!("Ledger check aborted: {e}")),
+            }
+        })
+    }
+}
+
+/// `STORAGE_PATH` is writable and its filesystem has enough free space.
+pub struct DiskSpaceProbe {
+    pub path: PathBuf,
+    pub min_free_bytes: u64,
+}
+
+impl HealthProbe for DiskSpaceProbe {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    fn critical(&self) -> bool {
+        true
+    }
+
+    fn check(&self) -> ProbeFuture<'_> {
+        Box::pin(async move {
+            let marker = self.path.join(format!(".probe-{}", uuid::Uuid::new_v4()));
+            if let Err(e) = tokio::fs::write(&marker, b"probe").await {
+                return ProbeOutcome::unhealthy(format!("Storage {} not writable: {e}", self.path.display()));
+            }
+            tokio::fs::remove_file(&marker).await.ok();
+
+            match fs2::available_space(&self.path) {
+                Ok(free) if free >= self.min_free_bytes => {
+                    ProbeOutcome::healthy(format!("{} MiB free", free / (1024 * 1024)))
+                }
+                Ok(free) => ProbeOutcome::unhealthy(format!(
+                    "Only {} MiB free under {} (minimum {} MiB)",
+                    free / (1024 * 1024),
+                    self.path.display(),
+                    self.min_free_bytes / (1024 * 1024)
+                )),
+                Err(e) => ProbeOutcome::unhealthy(format!("Storage {}: {e}", self.path.display())),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, health::ProbeStatus};
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+    }
+
+    /// Copy the test-key registry, which lists `m.gguf` as the bytes `weights`.
+    fn write_signed_registry(dir: &Path) {
+        for name in [REGISTRY_FILE, SIGNATURE_FILE, PUBLIC_KEY_FILE] {
+            fs::copy(fixture("registry").join(name), dir.join(name)).unwrap();
+        }
+    }
+
+    fn record_verification(dir: &Path, file: &str, sha256: String) {
+        let metadata = fs::metadata(dir.join(file)).unwrap();
+        let modified = metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap();
+        let cache = json!({ "entries": { file: {
+            "len": metadata.len(),
+            "modified_secs": modified.as_secs(),
+            "modified_nanos": modified.subsec_nanos(),
+            "sha256": sha256,
+        }}});
+        fs::write(dir.join(VERIFICATION_CACHE_FILE), cache.to_string()).unwrap();
+    }
+
+    fn probe(dir: &TempDir, file: &str) -> LocalModelProbe {
+        LocalModelProbe {
+            config: LiveConfig::new(Config {
+                local_model_path: Some(dir.path().join(file).display().to_string()),
+                ..Config::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn local_model_states() {
+        let dir = TempDir::new().unwrap();
+        write_signed_registry(dir.path());
+
+        assert_eq!(probe(&dir, "m.gguf").inspect().status, ProbeStatus::Unhealthy);
+
+        fs::write(dir.path().join("m.gguf"), b"weights").unwrap();
+        assert_eq!(probe(&dir, "m.gguf").inspect().status, ProbeStatus::Degraded);
+
+        record_verification(dir.path(), "m.gguf", format!("{:x}", Sha256::digest(b"weights")));
+        assert_eq!(probe(&dir, "m.gguf").inspect().status, ProbeStatus::Healthy);
+
+        record_verification(dir.path(), "m.gguf", "00".repeat(32));
+        assert_eq!(probe(&dir, "m.gguf").inspect().status, ProbeStatus::Unhealthy);
+
+        fs::write(dir.path().join("other.gguf"), b"x").unwrap();
+        assert_eq!(probe(&dir, "other.gguf").inspect().status, ProbeStatus::Degraded);
+    }
+
+    #[test]
+    fn edited_registry_is_not_trusted() {
+        let dir = TempDir::new().unwrap();
+        write_signed_registry(dir.path());
+        fs::write(dir.path().join("m.gguf"), b"weights").unwrap();
+        record_verification(dir.path(), "m.gguf", format!("{:x}", Sha256::digest(b"weights")));
+        let model = dir.path().join("m.gguf");
+        assert!(trusted_sha256(None, &model).is_some());
+
+        // Same listing, re-serialized: the signature no longer covers the bytes.
+        let registry: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.path().join(REGISTRY_FILE)).unwrap()).unwrap();
+        fs::write(dir.path().join(REGISTRY_FILE), registry.to_string()).unwrap();
+        let outcome = probe(&dir, "m.gguf").inspect();
+        assert_eq!(outcome.status, ProbeStatus::Degraded);
+        assert!(outcome.message.contains("unverified"), "{}", outcome.message);
+        assert!(trusted_sha256(None, &model).is_none());
+
+        fs::remove_file(dir.path().join(SIGNATURE_FILE)).unwrap();
+        assert_eq!(probe(&dir, "m.gguf").inspect().status, ProbeStatus::Degraded);
+    }
+
+    #[test]
+    fn verification_cache_matches_the_shared_fixture() {
+        let bytes = fs::read(fixture("models").join(VERIFICATION_CACHE_FILE)).unwrap();
+        let cache: VerificationCache = serde_json::from_slice(&bytes).unwrap();
+        let entry = &cache.entries["m.gguf"];
+        assert_eq!((entry.len, entry.modified_secs, entry.modified_nanos), (7, 1760948351, 125000000));
+        assert_eq!(entry.sha256, format!("{:x}", Sha256::digest(b"weights")));
+    }
+}
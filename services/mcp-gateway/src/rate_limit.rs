@@ -0,0 +1,262 @@
+//! Per-caller rate limiting. A caller is the authenticated principal (the `api_key` or
+//! `api_keys` entry its bearer token matches) or, for requests without a valid key, the
+//! peer IP; users sharing a proxy address therefore get their own quotas. Each caller has
+//! three buckets:
+//!
+//! - requests: `rate_limit_per_second` / `rate_limit_burst_size`, where each request
+//!   spends its route's `rate_limit_costs` weight (1 when unlisted);
+//! - online and local reasoning: `rate_limit_online_per_minute` and
+//!   `rate_limit_local_per_minute`, spent by the reasoning handlers, so MCP `reason`
+//!   calls count too.
+//!
+//! Quotas follow the live configuration, so reloads take effect on the next request. Callers
+//! whose buckets have refilled are pruned periodically, so the limiters only hold recent callers.
+
+use std::{
+    net::SocketAddr,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use governor::{
+    clock::{Clock, DefaultClock},
+    DefaultKeyedRateLimiter, InsufficientCapacity, Quota,
+};
+
+use tokio::task::JoinHandle;
+
+use crate::{auth::{self, Principal}, config::LiveConfig, error::ApiError, metrics::Backend};
+
+type Limiter = Arc<DefaultKeyedRateLimiter<String>>;
+
+/// One keyed limiter and the quota it was built for.
+struct Bucket {
+    current: Mutex<(Quota, Limiter)>,
+}
+
+impl Bucket {
+    fn new(quota: Quota) -> Self {
+        Self { current: Mutex::new((quota, Arc::new(governor::RateLimiter::keyed(quota)))) }
+    }
+
+    /// Spend `cost` cells of `key`'s quota; the limiter is rebuilt (forgetting past usage)
+    /// when the quota changed. Costs above the burst size are charged the whole burst.
+    fn check(&self, quota: Quota, key: &str, cost: u32) -> Result<(), ApiError> {
+        let limiter = {
+            let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+            if current.0 != quota {
+                *current = (quota, Arc::new(governor::RateLimiter::keyed(quota)));
+            }
+            Arc::clone(&current.1)
+        };
+        let cost = NonZeroU32::new(cost.min(quota.burst_size().get())).unwrap_or(NonZeroU32::MIN);
+        match limiter.check_key_n(&key.to_string(), cost) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(not_until)) => {
+                let wait = not_until.wait_time_from(DefaultClock::default().now());
+                let retry_after_secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                Err(ApiError::RateLimited { retry_after_secs })
+            }
+            // Costs are capped at the burst size, so this only guards a future change to that.
+            Err(InsufficientCapacity(_)) => {
+                let refill = quota.replenish_interval() * quota.burst_size().get();
+                Err(ApiError::RateLimited { retry_after_secs: refill.as_secs().max(1) })
+            }
+        }
+    }
+
+    /// Forget keys whose quota has fully refilled and release the memory they held.
+    fn prune(&self) {
+        let limiter = Arc::clone(&self.current.lock().unwrap_or_else(|e| e.into_inner()).1);
+        limiter.retain_recent();
+        limiter.shrink_to_fit();
+    }
+}
+
+pub struct RateLimiter {
+    config: LiveConfig,
+    requests: Bucket,
+    online: Bucket,
+    local: Bucket,
+}
+
+impl RateLimiter {
+    pub fn new(config: LiveConfig) -> Self {
+        let (requests, online, local) = quotas(&config);
+        Self {
+            requests: Bucket::new(requests),
+            online: Bucket::new(online),
+            local: Bucket::new(local),
+            config,
+        }
+    }
+
+    /// Charge a request to `path` against `caller`'s request bucket.
+    fn check_request(&self, caller: &str, path: &str) -> Result<(), ApiError> {
+        let cost = route_cost(&self.config.get().rate_limit_costs, path);
+        self.requests.check(quotas(&self.config).0, caller, cost)
+    }
+
+    /// Charge one reasoning call against `principal`'s bucket for `backend`.
+    pub fn check_reasoning(&self, principal: &Principal, backend: Backend) -> Result<(), ApiError> {
+        let (_, online, local) = quotas(&self.config);
+        match backend {
+            Backend::Online => self.online.check(online, &principal.0, 1),
+            Backend::Local => self.local.check(local, &principal.0, 1),
+        }
+    }
+
+    /// Drop callers indistinguishable from new ones from every bucket.
+    pub fn prune(&self) {
+        for bucket in [&self.requests, &self.online, &self.local] {
+            bucket.prune();
+        }
+    }
+}
+
+/// Prune `limiter` every `interval`, as tower_governor does, so one-off callers (each peer IP
+/// without a valid key) do not accumulate for the life of the process.
+pub fn spawn_pruner(limiter: Arc<RateLimiter>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            limiter.prune();
+        }
+    })
+}
+
+/// The request, online and local reasoning quotas.
+fn quotas(config: &LiveConfig) -> (Quota, Quota, Quota) {
+    let config = config.get();
+    let non_zero = |n: u32| NonZeroU32::new(n).unwrap_or(NonZeroU32::MIN);
+    (
+        Quota::per_second(non_zero(config.rate_limit_per_second))
+            .allow_burst(non_zero(config.rate_limit_burst_size)),
+        Quota::per_minute(non_zero(config.rate_limit_online_per_minute)),
+        Quota::per_minute(non_zero(config.rate_limit_local_per_minute)),
+    )
+}
+
+/// Parse a `rate_limit_costs` entry, `<route>=<cost>`.
+pub fn parse_cost(entry: &str) -> Result<(&str, u32)> {
+    let (route, cost) = entry.split_once('=').ok_or_else(|| anyhow!("expected <route>=<cost>"))?;
+    let cost = cost.trim().parse().context("cost must be a positive integer")?;
+    if !route.starts_with('/') || cost == 0 {
+        return Err(anyhow!("expected a route starting with / and a cost of at least 1"));
+    }
+    Ok((route.trim(), cost))
+}
+
+/// Weight of `path`; legacy aliases cost the same as their `/v1` route.
+fn route_cost(costs: &[String], path: &str) -> u32 {
+    let v1 = format!("/v1{path}");
+    costs
+        .iter()
+        .filter_map(|entry| parse_cost(entry).ok())
+        .find(|(route, _)| *route == path || *route == v1)
+        .map_or(1, |(_, cost)| cost)
+}
+
+/// Rejects requests over quota with `rate_limited` and a `Retry-After` in whole seconds.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let caller = match auth::principal(&limiter.config.get(), req.headers()) {
+        Some(principal) => format!("principal:{}", principal.0),
+        None => format!("ip:{}", peer.ip()),
+    };
+    match limiter.check_request(&caller, req.uri().path()) {
+        Ok(()) => next.run(req).await,
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn quota_changes_rebuild_the_limiter() {
+        let live = LiveConfig::new(Config {
+            rate_limit_per_second: 1,
+            rate_limit_burst_size: 1,
+            ..Config::default()
+        });
+        let limiter = RateLimiter::new(live.clone());
+        let ip = "ip:127.0.0.1";
+
+        assert!(limiter.check_request(ip, "/health").is_ok());
+        assert!(limiter.check_request(ip, "/health").is_err());
+
+        live.reload(&Config {
+            rate_limit_per_second: 1,
+            rate_limit_burst_size: 3,
+            ..Config::default()
+        })
+        .unwrap();
+        for _ in 0..3 {
+            assert!(limiter.check_request(ip, "/health").is_ok());
+        }
+        assert!(limiter.check_request(ip, "/health").is_err());
+    }
+
+    #[test]
+    fn callers_routes_and_backends_are_charged_separately() {
+        let limiter = RateLimiter::new(LiveConfig::new(Config {
+            rate_limit_per_second: 1,
+            rate_limit_burst_size: 10,
+            rate_limit_costs: vec!["/v1/verify=4".into()],
+            rate_limit_local_per_minute: 1,
+            ..Config::default()
+        }));
+
+        // Legacy aliases cost what their /v1 route costs.
+        assert!(limiter.check_request("principal:anna", "/v1/verify").is_ok());
+        assert!(limiter.check_request("principal:anna", "/verify").is_ok());
+        assert!(limiter.check_request("principal:anna", "/v1/verify").is_err());
+        assert!(limiter.check_request("principal:anna", "/health").is_ok());
+        assert!(limiter.check_request("principal:bruno", "/v1/verify").is_ok());
+
+        let anna = Principal("anna".into());
+        assert!(limiter.check_reasoning(&anna, Backend::Local).is_ok());
+        let Err(ApiError::RateLimited { retry_after_secs }) = limiter.check_reasoning(&anna, Backend::Local) else {
+            panic!("second local call within the minute should be limited");
+        };
+        assert!((1..=60).contains(&retry_after_secs));
+        assert!(limiter.check_reasoning(&anna, Backend::Online).is_ok());
+        assert!(limiter.check_reasoning(&Principal("bruno".into()), Backend::Local).is_ok());
+
+        assert!(parse_cost("/v1/query=0").is_err());
+        assert!(parse_cost("query=2").is_err());
+    }
+
+    #[test]
+    fn refilled_callers_are_pruned() {
+        let limiter = RateLimiter::new(LiveConfig::new(Config {
+            rate_limit_per_second: 1000,
+            rate_limit_burst_size: 1,
+            ..Config::default()
+        }));
+        let callers = |limiter: &RateLimiter| limiter.requests.current.lock().unwrap().1.len();
+
+        for i in 0..50 {
+            assert!(limiter.check_request(&format!("ip:10.0.0.{i}"), "/health").is_ok());
+        }
+        assert_eq!(callers(&limiter), 50);
+
+        std::thread::sleep(Duration::from_millis(10));
+        limiter.prune();
+        assert_eq!(callers(&limiter), 0);
+    }
+}
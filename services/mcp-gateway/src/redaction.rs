@@ -0,0 +1,93 @@
+//! Redaction of identifying values before text leaves the device.
+//! Applied to prompts sent to the online reasoner; local inference sees the original text.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::metrics::Metrics;
+
+/// Ordered so that longer structured identifiers are replaced before the
+/// looser phone pattern can match digits inside them.
+static PATTERNS: LazyLock<Vec<(&'static str, Regex, &'static str)>> = LazyLock::new(|| {
+    vec![
+        (
+            "iban",
+            Regex::new(r"(?i)\bIT\d{2}\s?[A-Z]\s?(?:\d\s?){10}(?:[0-9A-Z]\s?){11}[0-9A-Z]\b").unwrap(),
+            "[IBAN]",
+        ),
+        (
+            "codice_fiscale",
+            Regex::new(r"(?i)\b[A-Z]{6}\d{2}[A-EHLMPR-T]\d{2}[A-Z]\d{3}[A-Z]\b").unwrap(),
+            "[CODICE_FISCALE]",
+        ),
+        (
+            "email",
+            Regex::new(r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b").unwrap(),
+            "[EMAIL]",
+        ),
+        (
+            "phone",
+            Regex::new(r"(?:\+39[\s.-]?)?\b(?:3\d{2}|0\d{1,3})[\s.-]?\d{6,8}\b").unwrap(),
+            "[TELEFONO]",
+        ),
+    ]
+});
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Redacted {
+    pub text: String,
+    /// Number of replacements per kind, only for kinds that matched.
+    pub counts: Vec<(&'static str, usize)>,
+}
+
+pub fn redact(input: &str) -> Redacted {
+    let mut text = input.to_string();
+    let mut counts = Vec::new();
+    for (kind, pattern, placeholder) in PATTERNS.iter() {
+        let found = pattern.find_iter(&text).count();
+        if found > 0 {
+            text = pattern.replace_all(&text, *placeholder).into_owned();
+            counts.push((*kind, found));
+        }
+    }
+    Redacted { text, counts }
+}
+
+/// Redact `input` and record how many values of each kind were removed.
+pub fn redact_counted(metrics: &Metrics, input: &str) -> String {
+    let redacted = redact(input);
+    for (kind, count) in &redacted.counts {
+        metrics.record_redactions(kind, *count as u64);
+    }
+    redacted.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_identifiers_and_counts_each_kind() {
+        let r = redact(
+            "Mario Rossi (RSSMRA85T10A562S), mario.rossi@example.it, tel. +39 347 1234567, \
+             IBAN IT60X0542811101000000123456, altra mail m@b.com",
+        );
+        assert_eq!(
+            r.text,
+            "Mario Rossi ([CODICE_FISCALE]), [EMAIL], tel. [TELEFONO], IBAN [IBAN], altra mail [EMAIL]"
+        );
+        assert_eq!(
+            r.counts,
+            vec![("iban", 1), ("codice_fiscale", 1), ("email", 2), ("phone", 1)]
+        );
+    }
+
+    #[test]
+    fn leaves_legal_references_untouched() {
+        let text = "Cass. civ., Sez. III, 12/03/2021, n. 6789; art. 2043 c.c.; D.Lgs. 196/2003";
+        let r = redact(text);
+        assert_eq!(r.text, text);
+        assert!(r.counts.is_empty());
+    }
+}
@@ -1,161 +1,243 @@
-use axum::{http::StatusCode, Json, extract::State};
-use serde_json::json;
+use axum::{
+    extract::{multipart::MultipartRejection, Multipart, State},
+    Extension, Json,
+};
 use validator::Validate;
 
 use crate::{
-    gpt_client::{call_gpt, call_local_model},
-    model::*,
+    auth::Principal,
+    chunking,
+    documents::{self, DocumentKind},
+    error::{ApiError, ApiJson},
+    ledger::LedgerRecord,
+    metrics::Backend,
+    model::{self, *},
+    signing::SignatureStatus,
+    state::AppState,
+    verification::{self, Verdict},
 };
 
-/// Basic health endpoint
-pub async fn health() -> &'static str {
-    "ok"
-}
-
 /// Input sanitization & preprocessing step
-pub async fn query(Json(mut req): Json<QueryRequest>) -> Result<Json<ReasoningRequest>, (StatusCode, Json<serde_json::Value>)> {
-    // Validate input
-    if let Err(validation_errors) = req.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Validation failed",
-                "details": validation_errors
-            }))
-        ));
-    }
+#[utoipa::path(
+    post,
+    path = "/v1/query",
+    tag = "counsel-ai",
+    request_body = QueryRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sanitized prompt ready for `/v1/reason`, context fenced as untrusted data; documents cut to `context_document_bytes` are listed in `truncated`", body = ReasoningRequest),
+        (status = 400, description = "Invalid request body", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "A referenced document, matter or file does not exist", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "Referenced files exceed the workspace size limits", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "A referenced file is not PDF, DOCX or text", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Text contains instruction-injection patterns, or a path escaping the matter workspace", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limited; see `Retry-After`", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "A referenced file could not be read", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn query(
+    State(state): State<AppState>,
+    ApiJson(mut req): ApiJson<QueryRequest>,
+) -> Result<Json<ReasoningRequest>, ApiError> {
+    req.validate()?;
+    model::validation::validate_query_request(&mut req)?;
+    state.policy.check_prompt(&req.text)?;
 
-    // Sanitize and validate
-    if let Err(e) = model::validation::validate_query_request(&mut req) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Input validation failed",
-                "details": e.to_string()
-            }))
-        ));
+    let files = req.files.unwrap_or_default();
+    let mut context = if files.is_empty() {
+        Vec::new()
+    } else {
+        let matter = req
+            .matter
+            .ok_or_else(|| ApiError::validation("`files` need a `matter` whose workspace holds them"))?;
+        let workspaces = state.workspaces.clone();
+        tokio::task::spawn_blocking(move || workspaces.load(&matter, &files))
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Loading workspace files panicked");
+                ApiError::Storage("Failed to load the referenced files".to_string())
+            })??
+    };
+    for id in req.documents.unwrap_or_default() {
+        let (_, text) = state
+            .documents
+            .text(id)
+            .await
+            .map_err(|e| ApiError::Storage(format!("Failed to read document {id}: {e:#}")))?
+            .ok_or_else(|| ApiError::NotFound(format!("No document {id}")))?;
+        context.push(text);
     }
+    // Long documents are normalized like the query text, then cut at article, clause or motivo
+    // boundaries, never mid-comma; the cuts are reported to the caller.
+    let budget = state.config.get().context_document_bytes;
+    let mut truncated = Vec::new();
+    let mut context: Vec<String> = context
+        .iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let text = model::validation::normalize_text(text);
+            let (segment, omitted_sections) = chunking::context_segment(&text, budget);
+            if omitted_sections > 0 {
+                truncated.push(TruncatedContext { segment: i + 1, original_bytes: text.len(), omitted_sections });
+            }
+            segment
+        })
+        .collect();
+    state.policy.guard_context(&mut context, &state.metrics);
 
     let reason_req = ReasoningRequest {
         prompt: format!("Summarize and reason about: {}", req.text),
-        context: req.files.unwrap_or_default(),
+        context,
+        truncated,
     };
     
     Ok(Json(reason_req))
 }
 
 /// Online reasoning via GPT-5 (preferred if API key available)
-pub async fn reason(Json(mut reason_req): Json<ReasoningRequest>) -> Result<(StatusCode, Json<ReasoningResponse>), (StatusCode, Json<serde_json::Value>)> {
-    // Validate input
-    if let Err(validation_errors) = reason_req.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Validation failed",
-                "details": validation_errors
-            }))
-        ));
-    }
-
-    // Sanitize and validate
-    if let Err(e) = model::validation::validate_reasoning_request(&mut reason_req) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Input validation failed",
-                "details": e.to_string()
-            }))
-        ));
-    }
+#[utoipa::path(
+    post,
+    path = "/v1/reason",
+    tag = "counsel-ai",
+    request_body = ReasoningRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reasoned answer", body = ReasoningResponse),
+        (status = 400, description = "Invalid request body", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Prompt contains instruction-injection patterns", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Request or reasoning quota of the caller exhausted; see `Retry-After`", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "Online reasoner failed", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "No online reasoner configured and the local model is unavailable", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn reason(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    ApiJson(mut reason_req): ApiJson<ReasoningRequest>,
+) -> Result<Json<ReasoningResponse>, ApiError> {
+    reason_req.validate()?;
+    model::validation::validate_reasoning_request(&mut reason_req)?;
+    state.policy.check_prompt(&reason_req.prompt)?;
+    state.policy.guard_context(&mut reason_req.context, &state.metrics);
+    state.rate_limiter.check_reasoning(&principal, state.reasoner.backend())?;
 
-    match call_gpt(&reason_req).await {
-        Ok(res) => Ok((StatusCode::OK, Json(res))),
-        Err(err) => Ok((
-            StatusCode::BAD_GATEWAY,
-            Json(ReasoningResponse {
-                summary: format!("Online reasoning error: {err}"),
-                ..ReasoningResponse::default()
-            }),
-        )),
-    }
+    Ok(Json(state.reasoner.reason(&reason_req).await?))
 }
 
 /// Offline reasoning via local llama.cpp model (fallback)
-pub async fn reason_local(Json(mut req): Json<ReasoningRequest>) -> Result<(StatusCode, Json<ReasoningResponse>), (StatusCode, Json<serde_json::Value>)> {
-    // Validate input
-    if let Err(validation_errors) = req.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Validation failed",
-                "details": validation_errors
-            }))
-        ));
-    }
-
-    // Sanitize and validate
-    if let Err(e) = model::validation::validate_reasoning_request(&mut req) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Input validation failed",
-                "details": e.to_string()
-            }))
-        ));
-    }
+#[utoipa::path(
+    post,
+    path = "/v1/reason_local",
+    tag = "counsel-ai",
+    request_body = ReasoningRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Answer from the local model", body = ReasoningResponse),
+        (status = 400, description = "Invalid request body", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Prompt contains instruction-injection patterns", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Request or local reasoning quota of the caller exhausted; see `Retry-After`", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Local model missing or failed", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn reason_local(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    ApiJson(mut req): ApiJson<ReasoningRequest>,
+) -> Result<Json<ReasoningResponse>, ApiError> {
+    req.validate()?;
+    model::validation::validate_reasoning_request(&mut req)?;
+    state.policy.check_prompt(&req.prompt)?;
+    state.policy.guard_context(&mut req.context, &state.metrics);
+    state.rate_limiter.check_reasoning(&principal, Backend::Local)?;
 
-    match call_local_model(&req) {
-        Ok(res) => Ok((StatusCode::OK, Json(res))),
-        Err(err) => Ok((
-            StatusCode::BAD_REQUEST,
-            Json(ReasoningResponse {
-                summary: format!("Local model error: {err}"),
-                ..ReasoningResponse::default()
-            }),
-        )),
-    }
+    Ok(Json(state.reasoner.reason_locally(&req).await?))
 }
 
-/// Basic verification of reasoning output
-pub async fn verify(Json(mut resp): Json<ReasoningResponse>) -> Result<Json<VerifyResponse>, (StatusCode, Json<serde_json::Value>)> {
-    // Validate input
-    if let Err(validation_errors) = resp.validate() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Validation failed",
-                "details": validation_errors
-            }))
-        ));
+/// Verify reasoning output: check the gateway signature, check its citations against the
+/// legal knowledge base and, given the request context, how well each sentence is grounded in it
+#[utoipa::path(
+    post,
+    path = "/v1/verify",
+    tag = "counsel-ai",
+    request_body = VerifyRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Verification result with a verdict per citation and the grounding of the summary", body = VerifyResponse),
+        (status = 400, description = "Invalid request body", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limited; see `Retry-After`", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn verify(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<VerifyRequest>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    req.validate()?;
+    let resp = req.response;
+
+    if resp.summary.trim().is_empty() || resp.summary.len() >= 100000 {
+        let reason = if resp.summary.trim().is_empty() { "Empty reasoning response" } else { "Response too long" };
+        return Ok(Json(VerifyResponse {
+            valid: false,
+            reason: reason.to_string(),
+            signature: state.signer.verify(&resp),
+            citations: vec![],
+            grounding: None,
+        }));
     }
 
-    let valid = !resp.summary.trim().is_empty() && resp.summary.len() < 100000;
-    let message = if valid {
-        "Response appears valid".to_string()
-    } else if resp.summary.trim().is_empty() {
-        "Empty reasoning response".to_string()
+    // Citations are re-extracted from the summary rather than trusted from the request.
+    let citations = verification::check_citations(&state.kb, &resp.summary).await;
+    let flagged = citations.iter().filter(|c| c.verdict.is_flagged()).count();
+    let unchecked = citations.iter().filter(|c| c.verdict == Verdict::Unchecked).count();
+    let signature = state.signer.verify(&resp);
+    let (valid, reason) = if signature == SignatureStatus::Invalid {
+        (false, "Signature mismatch: the response was modified after signing".to_string())
+    } else if signature == SignatureStatus::UnknownKey {
+        (false, "Response was signed by another gateway key".to_string())
+    } else if flagged > 0 {
+        (false, format!("{flagged} of {} citations failed verification", citations.len()))
+    } else if unchecked > 0 {
+        (false, format!("{unchecked} citations could not be checked against the knowledge base"))
+    } else if signature == SignatureStatus::Missing {
+        (false, "Response is not signed by the gateway".to_string())
     } else {
-        "Response too long".to_string()
+        (true, "Response appears valid".to_string())
     };
-    
-    Ok(Json(VerifyResponse {
-        valid,
-        reason: message,
-    }))
+    // Unsupported statements are for the reviewer to weigh; they do not invalidate the response.
+    let grounding = (!req.context.is_empty()).then(|| verification::ground(&resp.summary, &req.context));
+
+    Ok(Json(VerifyResponse { valid, reason, signature, citations, grounding }))
 }
 
-/// Store reasoning logs locally
-pub async fn store(Json(payload): Json<serde_json::Value>) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+/// Store reasoning logs locally and chain them into the audit ledger
+#[utoipa::path(
+    post,
+    path = "/v1/store",
+    tag = "counsel-ai",
+    request_body(content = Object, description = "Any JSON value, typically a `ReasoningResponse`"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Stored and chained into the ledger", body = StoreResponse),
+        (status = 400, description = "Invalid request body", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "Payload larger than `max_request_size`", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limited; see `Retry-After`", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Log or ledger write failed", body = Problem, content_type = "application/problem+json")
+    )
+)]
+pub async fn store(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<serde_json::Value>,
+) -> Result<Json<StoreResponse>, ApiError> {
     // Validate payload size
+    let max_size = state.config.get().max_request_size;
     let payload_str = serde_json::to_string(&payload).unwrap_or_default();
-    if payload_str.len() > 1_000_000 { // 1MB limit
-        return Err((
-            StatusCode::PAYLOAD_TOO_LARGE,
-            Json(json!({
-                "error": "Payload too large",
-                "max_size": "1MB"
-            }))
-        ));
+    if payload_str.len() > max_size {
+        return Err(ApiError::PayloadTooLarge(format!("Payload exceeds {max_size} bytes")));
     }
 
     let id = uuid::Uuid::new_v4();
@@ -168,18 +250,103 @@ pub async fn store(Json(payload): Json<serde_json::Value>) -> Result<Json<serde_
         payload,
     };
 
-    match tokio::fs::write(
-        format!("/logs/{}.json", id),
-        serde_json::to_string_pretty(&entry).unwrap(),
+    state
+        .store
+        .write(&entry)
+        .await
+        .map_err(|e| ApiError::Storage(format!("Failed to store log entry: {e:#}")))?;
+
+    let appended = append_to_ledger(&state, "store", serde_json::to_value(&entry).unwrap_or_default()).await?;
+
+    Ok(Json(StoreResponse {
+        status: "stored".to_string(),
+        id,
+        chain_hash: appended.chain_hash,
+    }))
+}
+
+/// Upload a case document and extract its text for use as query context
+#[utoipa::path(
+    post,
+    path = "/v1/documents",
+    tag = "counsel-ai",
+    request_body(content = DocumentUpload, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Stored encrypted and chained into the ledger", body = DocumentResponse),
+        (status = 400, description = "Missing or empty `file` field", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "File larger than `max_document_size`, or a DOCX that decompresses past the extraction limit", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "Not PDF, DOCX or UTF-8 text, or no text could be extracted", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limited; see `Retry-After`", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Document or ledger write failed", body = Problem, content_type = "application/problem+json")
     )
-    .await {
-        Ok(_) => Ok(Json(json!({ "status": "stored", "id": id }))),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "Failed to store log entry",
-                "details": e.to_string()
-            }))
-        )),
+)]
+pub async fn upload_document(
+    State(state): State<AppState>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> Result<Json<DocumentResponse>, ApiError> {
+    let mut multipart = multipart?;
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("file") {
+            // Keep only the last path component of client-supplied names.
+            let filename = field
+                .file_name()
+                .and_then(|name| name.rsplit(['/', '\\']).next())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("document")
+                .to_string();
+            upload = Some((filename, field.bytes().await?));
+            break;
+        }
+    }
+    let (filename, bytes) = upload.ok_or_else(|| ApiError::validation("Missing `file` field"))?;
+    if bytes.is_empty() {
+        return Err(ApiError::validation("Uploaded file is empty"));
+    }
+
+    let kind = DocumentKind::detect(&bytes)
+        .ok_or_else(|| ApiError::UnsupportedMedia("Only PDF, DOCX and UTF-8 text files are supported".to_string()))?;
+    let original = bytes.clone();
+    let text = tokio::task::spawn_blocking(move || documents::extract_text(kind, &original))
+        .await
+        .map_err(|_| ApiError::UnsupportedMedia("The document could not be parsed".to_string()))?
+        .map_err(|e| documents::extraction_error(e, "Could not extract text"))?;
+    if text.is_empty() {
+        return Err(ApiError::UnsupportedMedia(
+            "No text found in the document (scanned PDFs need OCR first)".to_string(),
+        ));
     }
+
+    let meta = state
+        .documents
+        .put(&filename, kind, &bytes, &text)
+        .await
+        .map_err(|e| ApiError::Storage(format!("Failed to store document: {e:#}")))?;
+    // The ledger commits to the id and the hash of the original bytes.
+    let appended = append_to_ledger(&state, "document", serde_json::json!({ "id": meta.id, "sha256": meta.sha256 })).await?;
+
+    Ok(Json(DocumentResponse {
+        id: meta.id,
+        filename: meta.filename,
+        media_type: kind.media_type().to_string(),
+        sha256: meta.sha256,
+        size: meta.size,
+        characters: text.chars().count(),
+        chain_hash: appended.chain_hash,
+    }))
+}
+
+/// Append `record` to the audit ledger off the async runtime.
+async fn append_to_ledger(state: &AppState, action: &'static str, record: serde_json::Value) -> Result<LedgerRecord, ApiError> {
+    let ledger = state.ledger.clone();
+    tokio::task::spawn_blocking(move || ledger.append(action, &record))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r)
+        .map_err(|e| {
+            state.metrics.record_ledger_append_failure();
+            ApiError::Storage(format!("Failed to append audit ledger record: {e:#}"))
+        })
 }
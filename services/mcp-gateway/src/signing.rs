@@ -0,0 +1,220 @@
+//! Tamper evidence for reasoning output. Every [`ReasoningResponse`] the gateway produces
+//! is signed with a local Ed25519 key together with a digest of its request, the model
+//! id and hash, and a timestamp; `/verify` checks the signature, so an exported opinion
+//! can be proven to be unmodified gateway output.
+//!
+//! The key is a 32-byte seed at `signing_key_path`, created (mode 0600) on first start.
+//! The signed message is the JSON of the response fields and the signature metadata, in
+//! a fixed field order.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use utoipa::ToSchema;
+
+use crate::{
+    citations::CitationMatch,
+    model::{ReasoningRequest, ReasoningResponse, ResponseSignature},
+};
+
+pub const ALGORITHM: &str = "ed25519";
+
+/// Outcome of checking a response's signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// Signed by this gateway and unmodified since
+    Valid,
+    /// The response or its signature metadata was modified
+    Invalid,
+    /// Signed with a key this gateway does not hold
+    UnknownKey,
+    /// The response carries no signature
+    Missing,
+}
+
+/// What the signature covers, serialized in this field order.
+#[derive(Serialize)]
+struct SignedPayload<'a> {
+    summary: &'a str,
+    citations: &'a [CitationMatch],
+    algorithm: &'a str,
+    key_id: &'a str,
+    request_sha256: &'a str,
+    model: &'a str,
+    model_sha256: Option<&'a str>,
+    signed_at: String,
+}
+
+impl<'a> SignedPayload<'a> {
+    fn new(response: &'a ReasoningResponse, signature: &'a ResponseSignature) -> Result<Self> {
+        Ok(Self {
+            summary: &response.summary,
+            citations: &response.citations,
+            algorithm: &signature.algorithm,
+            key_id: &signature.key_id,
+            request_sha256: &signature.request_sha256,
+            model: &signature.model,
+            model_sha256: signature.model_sha256.as_deref(),
+            signed_at: signature.signed_at.format(&Rfc3339)?,
+        })
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+pub struct ResponseSigner {
+    key: SigningKey,
+    key_id: String,
+}
+
+impl ResponseSigner {
+    /// Load the key at `path`, creating it on first start.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        let seed = match fs::read(path) {
+            Ok(seed) => seed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => create_seed(path)?,
+            Err(e) => return Err(e).with_context(|| format!("failed to read signing key {}", path.display())),
+        };
+        let Ok(seed) = <[u8; 32]>::try_from(seed.as_slice()) else {
+            bail!("signing key {} is not a 32-byte Ed25519 seed", path.display());
+        };
+        Ok(Self::from_key(SigningKey::from_bytes(&seed)))
+    }
+
+    fn from_key(key: SigningKey) -> Self {
+        let digest = Sha256::digest(key.verifying_key().as_bytes());
+        let key_id = hex::encode(&digest[..8]);
+        Self { key, key_id }
+    }
+
+    /// Hex-encoded Ed25519 public key, for verifying signatures outside the gateway.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Attach a signature binding `response` to `request` and the model that produced it.
+    pub fn sign(
+        &self,
+        request: &ReasoningRequest,
+        response: &mut ReasoningResponse,
+        model: &str,
+        model_sha256: Option<String>,
+    ) -> Result<()> {
+        let mut signature = ResponseSignature {
+            algorithm: ALGORITHM.to_string(),
+            key_id: self.key_id.clone(),
+            request_sha256: hex::encode(Sha256::digest(serde_json::to_vec(request)?)),
+            model: model.to_string(),
+            model_sha256,
+            // RFC 3339 with whole seconds round-trips exactly.
+            signed_at: OffsetDateTime::now_utc().replace_nanosecond(0)?,
+            signature: String::new(),
+        };
+        let bytes = SignedPayload::new(response, &signature)?.bytes()?;
+        signature.signature = hex::encode(self.key.sign(&bytes).to_bytes());
+        response.signature = Some(signature);
+        Ok(())
+    }
+
+    pub fn verify(&self, response: &ReasoningResponse) -> SignatureStatus {
+        let Some(signature) = &response.signature else {
+            return SignatureStatus::Missing;
+        };
+        if signature.key_id != self.key_id {
+            return SignatureStatus::UnknownKey;
+        }
+        let valid = signature.algorithm == ALGORITHM
+            && (|| -> Result<bool> {
+                let bytes = SignedPayload::new(response, signature)?.bytes()?;
+                let sig = Signature::from_slice(&hex::decode(&signature.signature)?)?;
+                Ok(self.key.verifying_key().verify(&bytes, &sig).is_ok())
+            })()
+            .unwrap_or(false);
+        if valid {
+            SignatureStatus::Valid
+        } else {
+            SignatureStatus::Invalid
+        }
+    }
+}
+
+fn create_seed(path: &Path) -> Result<Vec<u8>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let key = SigningKey::generate(&mut aes_gcm::aead::OsRng);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create signing key {}", path.display()))?;
+    std::io::Write::write_all(&mut file, key.as_bytes())?;
+    tracing::info!(path = %path.display(), "Created response signing key");
+    Ok(key.to_bytes().to_vec())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A signer with a fresh in-memory key.
+    pub(crate) fn signer() -> ResponseSigner {
+        ResponseSigner::from_key(SigningKey::generate(&mut aes_gcm::aead::OsRng))
+    }
+
+    fn signed(signer: &ResponseSigner) -> ReasoningResponse {
+        let request = ReasoningRequest { prompt: "Parere".into(), context: vec!["Contratto".into()], truncated: vec![] };
+        let mut response = ReasoningResponse::new("Si applica l'art. 1218 c.c.".into());
+        signer.sign(&request, &mut response, "gpt-4", None).unwrap();
+        response
+    }
+
+    #[test]
+    fn key_is_created_once_and_reloaded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("keys/signing.key");
+        let first = ResponseSigner::load_or_create(&path).unwrap();
+        let again = ResponseSigner::load_or_create(&path).unwrap();
+        assert_eq!(first.public_key(), again.public_key());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::write(&path, b"short").unwrap();
+        assert!(ResponseSigner::load_or_create(&path).is_err());
+    }
+
+    #[test]
+    fn tampering_invalidates_the_signature() {
+        let gateway = signer();
+        let response = signed(&gateway);
+        assert_eq!(gateway.verify(&response), SignatureStatus::Valid);
+
+        // Survives the JSON round trip of an export.
+        let exported: ReasoningResponse = serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+        assert_eq!(gateway.verify(&exported), SignatureStatus::Valid);
+
+        let mut edited = signed(&gateway);
+        edited.summary.push_str(" Il ricorso è fondato.");
+        assert_eq!(gateway.verify(&edited), SignatureStatus::Invalid);
+
+        let mut relabelled = signed(&gateway);
+        relabelled.signature.as_mut().unwrap().model = "local".into();
+        assert_eq!(gateway.verify(&relabelled), SignatureStatus::Invalid);
+
+        assert_eq!(signer().verify(&response), SignatureStatus::UnknownKey);
+        assert_eq!(gateway.verify(&ReasoningResponse::new("x".into())), SignatureStatus::Missing);
+    }
+}
@@ -0,0 +1,88 @@
+//! Shared application state, handed to every handler and middleware through axum `State`.
+//! Built once from the validated [`Config`]; nothing downstream reads the environment.
+//! Handlers read settings through [`LiveConfig`], so reloadable ones apply without a restart.
+//! One pooled HTTP client is shared by the reasoners, the knowledge base and the health probes.
+//! The response signing key is loaded (or created) here, so a gateway that cannot sign does
+//! not start. Content policy rules are compiled once; changing them needs a restart.
+
+use std::sync::Arc;
+
+use crate::{
+    config::{Config, LiveConfig},
+    content_policy::ContentPolicy,
+    documents::DocumentStore,
+    gpt_client::Reasoner,
+    health::HealthRegistry,
+    kb::KnowledgeBase,
+    ledger::Ledger,
+    mcp_client::{ServerSpec, ToolHub},
+    metrics::Metrics,
+    rate_limit::RateLimiter,
+    signing::ResponseSigner,
+    store::LogStore,
+    workspace::Workspaces,
+};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: LiveConfig,
+    pub reasoner: Arc<Reasoner>,
+    pub kb: Arc<KnowledgeBase>,
+    pub store: Arc<LogStore>,
+    pub documents: Arc<DocumentStore>,
+    pub workspaces: Arc<Workspaces>,
+    pub ledger: Arc<Ledger>,
+    pub metrics: Arc<Metrics>,
+    pub health: Arc<HealthRegistry>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub signer: Arc<ResponseSigner>,
+    pub policy: Arc<ContentPolicy>,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("counsel-ai-mcp-gateway/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let metrics = Arc::new(Metrics::new());
+        let ledger = Arc::new(Ledger::new(&config.ledger_path));
+        let store = Arc::new(LogStore::new(&config.log_dir));
+        let kb = Arc::new(KnowledgeBase::new(&config, http.clone()));
+        let documents = Arc::new(DocumentStore::new(&config.storage_path, &config.encryption_key));
+        let workspaces = Arc::new(Workspaces::new(
+            &config.storage_path,
+            config.max_workspace_file_size,
+            config.max_workspace_total_size,
+        ));
+        let tools = Arc::new(ToolHub::new(
+            config.mcp_servers.iter().map(|entry| ServerSpec::parse(entry)).collect::<anyhow::Result<_>>()?,
+            http.clone(),
+            Arc::clone(&ledger),
+            Arc::clone(&metrics),
+        ));
+        let signer = Arc::new(ResponseSigner::load_or_create(&config.signing_key_path())?);
+        let policy = Arc::new(ContentPolicy::from_config(&config)?);
+        let config = LiveConfig::new(config);
+
+        Ok(Self {
+            reasoner: Arc::new(Reasoner::new(
+                &config,
+                http.clone(),
+                Arc::clone(&metrics),
+                tools,
+                Arc::clone(&signer),
+            )),
+            health: Arc::new(HealthRegistry::from_config(&config, http, Arc::clone(&ledger))),
+            rate_limiter: Arc::new(RateLimiter::new(config.clone())),
+            config,
+            kb,
+            store,
+            documents,
+            workspaces,
+            ledger,
+            metrics,
+            signer,
+            policy,
+        })
+    }
+}
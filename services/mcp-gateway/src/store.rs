@@ -0,0 +1,43 @@
+//! Plain JSON copies of stored log entries, one file per entry under `LOG_DIR`.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use uuid::Uuid;
+
+use crate::model::LogEntry;
+
+pub struct LogStore {
+    dir: PathBuf,
+}
+
+impl LogStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Write `entry` to `<dir>/<id>.json` and return the path.
+    pub async fn write(&self, entry: &LogEntry) -> Result<PathBuf> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.json", entry.id));
+        tokio::fs::write(&path, serde_json::to_string_pretty(entry)?)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Read back the entry stored under `id`, if any.
+    pub async fn read(&self, id: Uuid) -> Result<Option<LogEntry>> {
+        let path = self.dir.join(format!("{id}.json"));
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Checks behind `/verify`. Citations are cross-checked against the legal knowledge
+//! base: articles that do not exist, decisions cited with the wrong year or section and
+//! repealed provisions are flagged, one verdict per citation. Grounding scores each
+//! sentence of the summary by lexical overlap with the context it was reasoned from, so
+//! reviewers can tell model inference from what the documents say.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+use crate::{
+    chunking::{self, MAX_CHUNK_BYTES},
+    citations::{self, Citation, CitationMatch},
+    kb::KnowledgeBase,
+};
+
+/// Decisions sharing a number across years are few; this bounds the lookup.
+const LOOKUP_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The knowledge base holds the cited source as cited
+    Verified,
+    /// No such article, act or decision in the knowledge base
+    NotFound,
+    /// The decision number exists, but not in the cited year
+    WrongYear,
+    /// The decision exists in the cited year, but in another section or division
+    WrongSection,
+    /// The provision exists but is no longer in force
+    Repealed,
+    /// Too vague to check, e.g. a court without a decision number
+    Unverifiable,
+    /// The knowledge base could not be reached
+    Unchecked,
+}
+
+impl Verdict {
+    /// Whether the citation is known to be wrong.
+    pub fn is_flagged(self) -> bool {
+        matches!(self, Verdict::NotFound | Verdict::WrongYear | Verdict::WrongSection | Verdict::Repealed)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CitationVerdict {
+    pub canonical: String,
+    /// Byte span of the citation in `summary`
+    pub start: usize,
+    pub end: usize,
+    pub verdict: Verdict,
+    /// Why the citation was flagged, e.g. what the knowledge base holds instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Knowledge base entry that confirms the citation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kb_id: Option<String>,
+}
+
+/// A verdict for every citation in `text`, in order.
+pub async fn check_citations(kb: &KnowledgeBase, text: &str) -> Vec<CitationVerdict> {
+    let mut verdicts = Vec::new();
+    for found in citations::extract(text) {
+        let (verdict, detail, kb_id) = match check(kb, &found.citation).await {
+            Ok(outcome) => outcome,
+            Err(e) => (Verdict::Unchecked, Some(format!("{e:#}")), None),
+        };
+        let CitationMatch { canonical, start, end, .. } = found;
+        verdicts.push(CitationVerdict { canonical, start, end, verdict, detail, kb_id });
+    }
+    verdicts
+}
+
+type Outcome = (Verdict, Option<String>, Option<String>);
+
+async fn check(kb: &KnowledgeBase, citation: &Citation) -> Result<Outcome> {
+    match citation {
+        Citation::CodeArticle { code, articles, .. } => {
+            let fields = [("kind", json!("code_article")), ("code", json!(code))];
+            check_articles(kb, &fields, articles, &format!(" {}", code.abbreviation())).await
+        }
+        Citation::Legislation { act, number, year, articles } => {
+            let fields = [
+                ("kind", json!("legislation")),
+                ("act", json!(act)),
+                ("number", json!(number)),
+                ("year", json!(year)),
+            ];
+            if articles.is_empty() {
+                let hits = kb.lookup(&fields, 1).await?;
+                return Ok(match hits.into_iter().next() {
+                    None => (Verdict::NotFound, None, None),
+                    Some((id, payload)) => in_force(id, &payload),
+                });
+            }
+            let of = format!(" {} {number}/{year}", act.abbreviation());
+            check_articles(kb, &fields, articles, &of).await
+        }
+        Citation::CaseLaw { number: None, .. } | Citation::CaseLaw { year: None, .. } => {
+            Ok((Verdict::Unverifiable, Some("No decision number to check".to_string()), None))
+        }
+        Citation::CaseLaw { court, division, section, number: Some(number), year: Some(year) } => {
+            let fields = [("kind", json!("case_law")), ("court", json!(court)), ("number", json!(number))];
+            let hits = kb.lookup(&fields, LOOKUP_LIMIT).await?;
+            if hits.is_empty() {
+                return Ok((Verdict::NotFound, None, None));
+            }
+            let Some((id, payload)) = hits.iter().find(|(_, p)| p["year"] == json!(year)) else {
+                let years: Vec<String> = hits.iter().map(|(_, p)| p["year"].to_string()).collect();
+                let detail = format!("n. {number} exists for {}", years.join(", "));
+                return Ok((Verdict::WrongYear, Some(detail), None));
+            };
+            // Only details both sides state can disagree.
+            let differs = |key: &str, cited: Value| !payload[key].is_null() && !cited.is_null() && payload[key] != cited;
+            if differs("section", json!(section)) || differs("division", json!(division)) {
+                let mut held = String::new();
+                if let Some(division) = payload["division"].as_str() {
+                    held.push_str(division);
+                }
+                if let Some(section) = payload["section"].as_str() {
+                    held.push_str(&format!("{}Sez. {section}", if held.is_empty() { "" } else { ", " }));
+                }
+                return Ok((Verdict::WrongSection, Some(format!("Decided by {held}")), Some(id.clone())));
+            }
+            Ok((Verdict::Verified, None, Some(id.clone())))
+        }
+    }
+}
+
+/// Every article must exist and be in force; the first failure decides.
+async fn check_articles(
+    kb: &KnowledgeBase,
+    fields: &[(&str, Value)],
+    articles: &[String],
+    of: &str,
+) -> Result<Outcome> {
+    let mut confirmed = None;
+    for article in articles {
+        let mut fields = fields.to_vec();
+        fields.push(("article", json!(article)));
+        let Some((id, payload)) = kb.lookup(&fields, 1).await?.into_iter().next() else {
+            return Ok((Verdict::NotFound, Some(format!("art. {article}{of} does not exist")), None));
+        };
+        match in_force(id, &payload) {
+            (Verdict::Verified, _, id) => confirmed = confirmed.or(id),
+            (verdict, detail, id) => {
+                let detail = detail.map(|d| format!("art. {article}{of}: {d}"));
+                return Ok((verdict, detail, id));
+            }
+        }
+    }
+    Ok((Verdict::Verified, None, confirmed))
+}
+
+fn in_force(id: String, payload: &Value) -> Outcome {
+    if payload["status"] != "repealed" {
+        return (Verdict::Verified, None, Some(id));
+    }
+    let detail = match payload["repealed_by"].as_str() {
+        Some(by) => format!("repealed by {by}"),
+        None => "repealed".to_string(),
+    };
+    (Verdict::Repealed, Some(detail), Some(id))
+}
+
+/// Share of a sentence's terms a context passage must contain for it to count as supported.
+pub const SUPPORT_THRESHOLD: f32 = 0.5;
+/// Sentences with fewer terms (headings, "Si conclude.") are not scored.
+const MIN_TERMS: usize = 3;
+/// Terms are cut to this many characters, a light stemming that matches
+/// "risarcimento" with "risarcire" and "contratto" with "contrattuale".
+const STEM_CHARS: usize = 6;
+
+const STOPWORDS: &[&str] = &[
+    "alla", "alle", "agli", "allo", "anche", "che", "chi", "come", "con", "cui", "dal", "dalla", "dalle",
+    "dai", "degli", "dei", "del", "della", "delle", "dello", "due", "essere", "fra", "gli", "hanno", "lei",
+    "loro", "lui", "nel", "nella", "nelle", "negli", "nei", "non", "per", "più", "poi", "quale", "quali",
+    "quando", "quanto", "questa", "queste", "questi", "questo", "sia", "sono", "stato", "sua", "sue",
+    "sul", "sulla", "suo", "suoi", "tale", "tali", "tra", "una", "uno", "the", "and", "that", "with",
+];
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Grounding {
+    /// Mean support of the scored sentences, 0 to 1
+    pub score: f32,
+    /// Number of sentences scored
+    pub sentences: usize,
+    /// Sentences whose support is below the threshold, in order
+    pub unsupported: Vec<UnsupportedStatement>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UnsupportedStatement {
+    pub text: String,
+    /// Byte span of the sentence in `summary`
+    pub start: usize,
+    pub end: usize,
+    /// Share of the sentence's terms found in the closest context passage
+    pub score: f32,
+    /// Index of the context segment holding the closest passage, if any term matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closest_context: Option<usize>,
+}
+
+/// Score every sentence of `summary` against the passages of `context`.
+pub fn ground(summary: &str, context: &[String]) -> Grounding {
+    let passages: Vec<(usize, HashSet<String>)> = context
+        .iter()
+        .enumerate()
+        .flat_map(|(index, segment)| {
+            chunking::chunk(segment, MAX_CHUNK_BYTES)
+                .into_iter()
+                .map(move |chunk| (index, terms(&chunk.text).collect()))
+        })
+        .collect();
+
+    let mut scored = 0;
+    let mut total = 0.0;
+    let mut unsupported = Vec::new();
+    for (start, end) in sentences(summary) {
+        let text = &summary[start..end];
+        let sentence: HashSet<String> = terms(text).collect();
+        if sentence.len() < MIN_TERMS {
+            continue;
+        }
+        let (score, closest) = passages
+            .iter()
+            .map(|(index, passage)| {
+                let shared = sentence.iter().filter(|t| passage.contains(*t)).count();
+                (shared as f32 / sentence.len() as f32, *index)
+            })
+            .fold((0.0, None), |best, (score, index)| if score > best.0 { (score, Some(index)) } else { best });
+        scored += 1;
+        total += score;
+        if score < SUPPORT_THRESHOLD {
+            unsupported.push(UnsupportedStatement {
+                text: text.to_string(),
+                start,
+                end,
+                score,
+                closest_context: closest,
+            });
+        }
+    }
+    Grounding {
+        score: if scored == 0 { 1.0 } else { total / scored as f32 },
+        sentences: scored,
+        unsupported,
+    }
+}
+
+/// Content terms of `text`: lowercased words and numbers, stopwords dropped, stemmed.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3 || (!word.is_empty() && word.chars().all(|c| c.is_ascii_digit())))
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .map(|word| word.chars().take(STEM_CHARS).collect())
+}
+
+/// Byte spans of the sentences of `text`. A sentence ends at `.`, `!` or `?` followed by
+/// whitespace and a capital letter, or at a line break; never inside a citation, so
+/// "art. 1218 c.c." and "Cass. civ., Sez. III" stay whole.
+fn sentences(text: &str) -> Vec<(usize, usize)> {
+    let citations = citations::extract(text);
+    let in_citation = |at: usize| citations.iter().any(|c| c.start < at && at < c.end);
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for (at, c) in text.char_indices() {
+        let end = at + c.len_utf8();
+        let boundary = match c {
+            '\n' => true,
+            '.' | '!' | '?' => {
+                let rest = text[end..].trim_start();
+                let spaced = rest.len() < text.len() - end;
+                spaced && rest.starts_with(char::is_uppercase) && !in_citation(end)
+            }
+            _ => false,
+        };
+        if boundary {
+            push_trimmed(text, start, end, &mut spans);
+            start = end;
+        }
+    }
+    push_trimmed(text, start, text.len(), &mut spans);
+    spans
+}
+
+fn push_trimmed(text: &str, start: usize, end: usize, spans: &mut Vec<(usize, usize)>) {
+    let slice = &text[start..end];
+    let trimmed = slice.trim();
+    if !trimmed.is_empty() {
+        let offset = start + (slice.len() - slice.trim_start().len());
+        spans.push((offset, offset + trimmed.len()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{routing::post, Json, Router};
+    use reqwest::Client;
+
+    /// A Qdrant stand-in answering scroll requests by exact payload match.
+    async fn fake_qdrant() -> String {
+        let points = json!([
+            { "id": 1, "payload": { "kind": "code_article", "code": "codice_civile", "article": "1218" } },
+            { "id": 2, "payload": { "kind": "code_article", "code": "codice_civile", "article": "1453" } },
+            { "id": 3, "payload": { "kind": "code_article", "code": "codice_civile", "article": "2112",
+                                    "status": "repealed", "repealed_by": "d.lgs. 18/2001" } },
+            { "id": 4, "payload": { "kind": "legislation", "act": "decreto_legislativo", "number": 196, "year": 2003 } },
+            { "id": 5, "payload": { "kind": "case_law", "court": "cassazione", "number": 30574, "year": 2022,
+                                    "division": "civile", "section": "III" } },
+        ]);
+        let app = Router::new().route(
+            "/collections/legal_kb/points/scroll",
+            post(move |Json(body): Json<Value>| async move {
+                let must = body["filter"]["must"].as_array().unwrap().clone();
+                let matching: Vec<&Value> = points
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter(|p| must.iter().all(|m| p["payload"][m["key"].as_str().unwrap()] == m["match"]["value"]))
+                    .collect();
+                Json(json!({ "result": { "points": matching } }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    async fn verdicts(kb: &KnowledgeBase, text: &str) -> Vec<(Verdict, Option<String>)> {
+        check_citations(kb, text).await.into_iter().map(|v| (v.verdict, v.detail)).collect()
+    }
+
+    #[tokio::test]
+    async fn citations_are_checked_against_the_kb() {
+        let config = Config { vector_db_url: fake_qdrant().await, ..Config::default() };
+        let kb = KnowledgeBase::new(&config, Client::new());
+
+        let found = check_citations(&kb, "Ai sensi dell'art. 1218 c.c. e del d.lgs. 196/2003").await;
+        assert_eq!(found.iter().map(|v| v.verdict).collect::<Vec<_>>(), [Verdict::Verified; 2]);
+        assert_eq!((found[0].kb_id.as_deref(), found[0].start), (Some("1"), 14));
+
+        assert_eq!(
+            verdicts(&kb, "artt. 1453 e 9999 c.c.").await,
+            [(Verdict::NotFound, Some("art. 9999 c.c. does not exist".into()))]
+        );
+        assert_eq!(
+            verdicts(&kb, "art. 2112 c.c.").await,
+            [(Verdict::Repealed, Some("art. 2112 c.c.: repealed by d.lgs. 18/2001".into()))]
+        );
+        assert_eq!(verdicts(&kb, "L. 241/1990").await, [(Verdict::NotFound, None)]);
+    }
+
+    #[tokio::test]
+    async fn decisions_must_match_year_and_section() {
+        let config = Config { vector_db_url: fake_qdrant().await, ..Config::default() };
+        let kb = KnowledgeBase::new(&config, Client::new());
+
+        assert_eq!(verdicts(&kb, "Cass. civ., Sez. III, n. 30574/2022").await, [(Verdict::Verified, None)]);
+        assert_eq!(verdicts(&kb, "Cass. n. 30574/2022").await, [(Verdict::Verified, None)]);
+        assert_eq!(
+            verdicts(&kb, "Cass. civ., Sez. III, n. 30574/2021").await,
+            [(Verdict::WrongYear, Some("n. 30574 exists for 2022".into()))]
+        );
+        assert_eq!(
+            verdicts(&kb, "Cass. civ., Sez. I, n. 30574/2022").await,
+            [(Verdict::WrongSection, Some("Decided by civile, Sez. III".into()))]
+        );
+        assert_eq!(verdicts(&kb, "Cass. civ., n. 12345/2022").await, [(Verdict::NotFound, None)]);
+        assert_eq!(verdicts(&kb, "Cons. Stato").await[0].0, Verdict::Unverifiable);
+
+        let offline = KnowledgeBase::new(
+            &Config { vector_db_url: "http://127.0.0.1:9".into(), ..Config::default() },
+            Client::new(),
+        );
+        assert_eq!(verdicts(&offline, "art. 1218 c.c.").await[0].0, Verdict::Unchecked);
+    }
+
+    #[test]
+    fn sentences_keep_citations_whole() {
+        let text = "Si applica l'art. 1218 c.c. al caso. Lo conferma Cass. civ., Sez. III, n. 30574/2022! Resta il dubbio?\nFine";
+        let spans: Vec<&str> = sentences(text).into_iter().map(|(s, e)| &text[s..e]).collect();
+        assert_eq!(
+            spans,
+            [
+                "Si applica l'art. 1218 c.c. al caso.",
+                "Lo conferma Cass. civ., Sez. III, n. 30574/2022!",
+                "Resta il dubbio?",
+                "Fine"
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_sentences_are_reported() {
+        let context = vec![
+            "Art. 1. Il conduttore non ha pagato il canone di locazione per sei mesi consecutivi.".to_string(),
+            "Il locatore ha inviato una diffida ad adempiere il 3 marzo 2024.".to_string(),
+        ];
+        let summary = "Il conduttore non ha pagato il canone per sei mesi. \
+                       Il locatore ha inviato diffida ad adempiere. \
+                       Il giudice condannerà certamente il conduttore al risarcimento dei danni morali.";
+        let grounding = ground(summary, &context);
+        assert_eq!(grounding.sentences, 3);
+        assert_eq!(grounding.unsupported.len(), 1);
+        let claim = &grounding.unsupported[0];
+        assert!(claim.text.starts_with("Il giudice condannerà"));
+        assert_eq!(&summary[claim.start..claim.end], claim.text);
+        assert!(claim.score < SUPPORT_THRESHOLD && grounding.score > SUPPORT_THRESHOLD);
+
+        let none = ground(summary, &[]);
+        assert_eq!((none.unsupported.len(), none.score), (3, 0.0));
+    }
+}
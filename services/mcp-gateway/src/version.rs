@@ -0,0 +1,109 @@
+//! API versioning: `/version` for feature negotiation, and the deprecated
+//! unversioned aliases of the `/v1` business routes.
+
+use axum::{
+    extract::{Request, State},
+    http::{header::LINK, HeaderValue},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+
+pub const API_VERSION: &str = "v1";
+
+/// When the unversioned aliases were deprecated (RFC 9745 `Deprecation`: `@<unix seconds>`).
+const LEGACY_DEPRECATION: &str = "@1793491200"; // 2026-11-01T00:00:00Z
+/// When the unversioned aliases will be removed (RFC 8594 `Sunset`).
+const LEGACY_SUNSET: &str = "Sat, 01 May 2027 00:00:00 GMT";
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VersionResponse {
+    /// Current API version; business routes live under `/<api_version>`
+    pub api_version: String,
+    /// Gateway release
+    pub gateway_version: String,
+    pub build: BuildInfo,
+    /// Stable feature names clients may branch on
+    pub capabilities: Vec<String>,
+    /// Unversioned paths still served as deprecated aliases
+    pub deprecated_aliases: Vec<String>,
+    /// RFC 8594 sunset date of the deprecated aliases
+    pub sunset: String,
+    /// Hex Ed25519 public key that signs reasoning responses
+    pub response_signing_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BuildInfo {
+    /// Commit the binary was built from (`COUNSEL_BUILD_SHA` at compile time)
+    pub git_sha: Option<String>,
+    /// Build time (`COUNSEL_BUILD_DATE` at compile time)
+    pub built_at: Option<String>,
+    pub profile: String,
+}
+
+/// Features this gateway offers with the current configuration.
+pub fn capabilities(state: &AppState) -> Vec<String> {
+    let config = state.config.get();
+    let mut capabilities = vec!["reason.local", "verify", "store.ledger", "errors.problem_json", "metrics", "mcp", "documents", "responses.signed"];
+    if config.openai_api_key.is_some() {
+        capabilities.push("reason.online");
+    }
+    if !config.mcp_servers.is_empty() {
+        capabilities.push("mcp.tools");
+    }
+    capabilities.into_iter().map(String::from).collect()
+}
+
+/// API version, build and capability report
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "counsel-ai",
+    responses((status = 200, description = "Version and capabilities", body = VersionResponse))
+)]
+pub async fn version(State(state): State<AppState>) -> Json<VersionResponse> {
+    Json(VersionResponse {
+        api_version: API_VERSION.to_string(),
+        gateway_version: env!("CARGO_PKG_VERSION").to_string(),
+        build: BuildInfo {
+            git_sha: build_var(option_env!("COUNSEL_BUILD_SHA")),
+            built_at: build_var(option_env!("COUNSEL_BUILD_DATE")),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        },
+        capabilities: capabilities(&state),
+        deprecated_aliases: crate::api_routes(&state.config.get())
+            .iter()
+            .filter_map(|(_, path, _)| legacy_alias(path))
+            .map(String::from)
+            .collect(),
+        sunset: LEGACY_SUNSET.to_string(),
+        response_signing_key: state.signer.public_key(),
+    })
+}
+
+/// Docker sets unsupplied build args to empty strings.
+fn build_var(value: Option<&str>) -> Option<String> {
+    value.filter(|v| !v.is_empty()).map(String::from)
+}
+
+/// The deprecated unversioned path served alongside a `/v1` route.
+pub fn legacy_alias(path: &str) -> Option<&str> {
+    path.strip_prefix("/v1").filter(|alias| alias.starts_with('/'))
+}
+
+/// Marks a response from an unversioned alias as deprecated and points at its `/v1` successor.
+pub async fn deprecated_alias(State(successor): State<&'static str>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static(LEGACY_DEPRECATION));
+    headers.insert("sunset", HeaderValue::from_static(LEGACY_SUNSET));
+    if let Ok(link) = HeaderValue::from_str(&format!("<{successor}>; rel=\"successor-version\"")) {
+        headers.insert(LINK, link);
+    }
+    response
+}
@@ -0,0 +1,179 @@
+//! Per-matter workspaces: the folder `<storage_path>/matters/<matter>` holding documents a
+//! lawyer can reference from `QueryRequest.files`. Paths are resolved with symlinks
+//! followed and must stay inside the matter folder; referenced files are loaded as
+//! context within per-file and per-request size limits.
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    documents::{self, DocumentKind},
+    error::ApiError,
+};
+
+pub struct Workspaces {
+    root: PathBuf,
+    max_file_size: u64,
+    max_total_size: u64,
+}
+
+impl Workspaces {
+    pub fn new(storage_path: impl AsRef<Path>, max_file_size: u64, max_total_size: u64) -> Self {
+        Self {
+            root: storage_path.as_ref().join("matters"),
+            max_file_size,
+            max_total_size,
+        }
+    }
+
+    /// The canonical folder of `matter`, which must itself resolve inside the matters root.
+    fn matter_root(&self, matter: &str) -> Result<PathBuf, ApiError> {
+        if matter.is_empty()
+            || matter.len() > 64
+            || !matter.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(ApiError::validation(
+                "Matter ids are 1-64 letters, digits, `_` or `-`",
+            ));
+        }
+        let missing = || ApiError::NotFound(format!("No workspace for matter {matter}"));
+        let matters = self.root.canonicalize().map_err(|_| missing())?;
+        let root = matters.join(matter).canonicalize().map_err(|_| missing())?;
+        if !root.starts_with(&matters) || !root.is_dir() {
+            return Err(ApiError::PolicyViolation(format!(
+                "Workspace for matter {matter} resolves outside the storage root"
+            )));
+        }
+        Ok(root)
+    }
+
+    /// Read and extract the text of each file, in order. Blocking: call from a blocking thread.
+    pub fn load(&self, matter: &str, files: &[String]) -> Result<Vec<String>, ApiError> {
+        let root = self.matter_root(matter)?;
+        let mut total = 0;
+        let mut texts = Vec::with_capacity(files.len());
+        for relative in files {
+            let path = resolve_in(&root, relative)?;
+            let bytes = self.read_limited(&path, relative)?;
+            total += bytes.len() as u64;
+            if total > self.max_total_size {
+                return Err(ApiError::PayloadTooLarge(format!(
+                    "Referenced files exceed {} bytes in total",
+                    self.max_total_size
+                )));
+            }
+            let kind = DocumentKind::detect(&bytes)
+                .ok_or_else(|| ApiError::UnsupportedMedia(format!("{relative} is not PDF, DOCX or UTF-8 text")))?;
+            let text = documents::extract_text(kind, &bytes).map_err(|e| documents::extraction_error(e, relative))?;
+            texts.push(text);
+        }
+        Ok(texts)
+    }
+
+    /// Read at most `max_file_size` bytes; the limit holds even if the file grows after the check.
+    fn read_limited(&self, path: &Path, relative: &str) -> Result<Vec<u8>, ApiError> {
+        let too_large = || ApiError::PayloadTooLarge(format!("{relative} exceeds {} bytes", self.max_file_size));
+        let file = fs::File::open(path).map_err(|e| ApiError::Storage(format!("Failed to open {relative}: {e}")))?;
+        if file.metadata().map(|m| m.len()).unwrap_or(0) > self.max_file_size {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::new();
+        file.take(self.max_file_size + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| ApiError::Storage(format!("Failed to read {relative}: {e}")))?;
+        if bytes.len() as u64 > self.max_file_size {
+            return Err(too_large());
+        }
+        Ok(bytes)
+    }
+}
+
+/// Resolve `relative` inside `root`, following symlinks.
+fn resolve_in(root: &Path, relative: &str) -> Result<PathBuf, ApiError> {
+    let path = root
+        .join(relative)
+        .canonicalize()
+        .map_err(|_| ApiError::NotFound(format!("No file {relative} in the matter workspace")))?;
+    if !path.starts_with(root) {
+        return Err(ApiError::PolicyViolation(format!(
+            "{relative} resolves outside the matter workspace"
+        )));
+    }
+    if !path.is_file() {
+        return Err(ApiError::validation(format!("{relative} is not a file")));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace(max_file: u64, max_total: u64) -> (TempDir, Workspaces) {
+        let dir = TempDir::new().unwrap();
+        let matter = dir.path().join("matters").join("HT-2025-0001");
+        fs::create_dir_all(matter.join("atti")).unwrap();
+        fs::write(matter.join("atti/citazione.txt"), "Atto di citazione").unwrap();
+        fs::write(matter.join("memoria.txt"), "Memoria difensiva").unwrap();
+        fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+        let workspaces = Workspaces::new(dir.path(), max_file, max_total);
+        (dir, workspaces)
+    }
+
+    fn resolve(workspaces: &Workspaces, matter: &str, relative: &str) -> Result<PathBuf, ApiError> {
+        resolve_in(&workspaces.matter_root(matter)?, relative)
+    }
+
+    fn code(result: Result<impl std::fmt::Debug, ApiError>) -> &'static str {
+        result.unwrap_err().code()
+    }
+
+    #[test]
+    fn loads_files_inside_the_matter() {
+        let (_dir, workspaces) = workspace(1024, 1024);
+        let files = ["atti/citazione.txt".to_string(), "./memoria.txt".to_string()];
+        assert_eq!(
+            workspaces.load("HT-2025-0001", &files).unwrap(),
+            ["Atto di citazione", "Memoria difensiva"]
+        );
+        assert_eq!(code(workspaces.load("HT-2025-9999", &files)), "not_found");
+        assert_eq!(code(workspaces.load("../HT-2025-0001", &files)), "validation_failed");
+        assert_eq!(code(resolve(&workspaces, "HT-2025-0001", "atti")), "validation_failed");
+    }
+
+    #[test]
+    fn rejects_paths_escaping_the_matter() {
+        let (dir, workspaces) = workspace(1024, 1024);
+        let absolute = dir.path().join("secret.txt").display().to_string();
+        assert_eq!(code(resolve(&workspaces, "HT-2025-0001", "../../secret.txt")), "policy_violation");
+        assert_eq!(code(resolve(&workspaces, "HT-2025-0001", &absolute)), "policy_violation");
+
+        #[cfg(unix)]
+        {
+            let matter = dir.path().join("matters/HT-2025-0001");
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), matter.join("link.txt")).unwrap();
+            std::os::unix::fs::symlink(dir.path(), matter.join("up")).unwrap();
+            assert_eq!(code(resolve(&workspaces, "HT-2025-0001", "link.txt")), "policy_violation");
+            assert_eq!(code(resolve(&workspaces, "HT-2025-0001", "up/secret.txt")), "policy_violation");
+
+            // A matter folder that is itself a symlink out of the storage root.
+            std::os::unix::fs::symlink(dir.path(), dir.path().join("matters/escape")).unwrap();
+            assert_eq!(code(resolve(&workspaces, "escape", "secret.txt")), "policy_violation");
+        }
+    }
+
+    #[test]
+    fn enforces_size_limits() {
+        let (_dir, per_file) = workspace(10, 1024);
+        assert_eq!(code(per_file.load("HT-2025-0001", &["memoria.txt".into()])), "payload_too_large");
+
+        let (_dir, total) = workspace(1024, 20);
+        let both = ["atti/citazione.txt".to_string(), "memoria.txt".to_string()];
+        assert!(total.load("HT-2025-0001", &both[..1]).is_ok());
+        assert_eq!(code(total.load("HT-2025-0001", &both)), "payload_too_large");
+    }
+}
@@ -0,0 +1,421 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Callers report progress to the user at most once per this many bytes.
+pub const PROGRESS_STEP: u64 = 8 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A single model file to fetch and verify.
+#[derive(Debug, Clone)]
+pub struct DownloadRequest<'a> {
+    pub url: &'a str,
+    pub dest: &'a Path,
+    /// Expected lowercase hex SHA-256 of the complete file.
+    pub sha256: &'a str,
+    /// Size advertised by the registry, used for the free-space pre-check.
+    pub expected_size: Option<u64>,
+}
+
+/// Reported after every chunk written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// Path of the partial download kept next to `dest` between attempts.
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Download `request.url` into `request.dest`, resuming any previous `.part` file.
+///
+/// The file is hashed while it streams and only renamed into place once the digest
+/// matches; on mismatch the partial file is discarded so the next attempt starts clean.
+/// Returns the verified digest.
+pub fn download_verified(
+    request: &DownloadRequest<'_>,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<String> {
+    let part = partial_path(request.dest);
+    let parent = request
+        .dest
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(&part)
+        .with_context(|| format!("failed to open {}", part.display()))?;
+    let mut offset = file.metadata()?.len();
+
+    if let Some(expected) = request.expected_size {
+        ensure_free_space(parent, expected.saturating_sub(offset))?;
+    }
+
+    // Resume the digest over the bytes we already have.
+    let mut hasher = Sha256::new();
+    hash_existing(&mut file, &mut hasher)?;
+
+    let client = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(None)
+        .build()?;
+    let mut response = fetch(&client, request.url, offset)?;
+    if response.status() == StatusCode::PARTIAL_CONTENT && content_range(&response).map(|r| r.0) != Some(offset) {
+        // The server sent a different slice than the one asked for: start over.
+        file.set_len(0)?;
+        hasher = Sha256::new();
+        offset = 0;
+        response = fetch(&client, request.url, 0)?;
+    }
+
+    let total = match response.status() {
+        StatusCode::PARTIAL_CONTENT => content_range(&response).and_then(|r| r.1).or(request.expected_size),
+        // The server ignored the range (or there was nothing to resume): start over.
+        StatusCode::OK => {
+            if offset > 0 {
+                file.set_len(0)?;
+                hasher = Sha256::new();
+                offset = 0;
+            }
+            response.content_length().or(request.expected_size)
+        }
+        // The partial file already holds every byte; fall through to verification.
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => Some(offset),
+        status => bail!("download of {} failed with HTTP {}", request.url, status),
+    };
+
+    let mut downloaded = offset;
+    on_progress(Progress { downloaded, total });
+    if response.status() != StatusCode::RANGE_NOT_SATISFIABLE {
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        loop {
+            let read = response
+                .read(&mut buffer)
+                .with_context(|| format!("connection to {} interrupted", request.url))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])?;
+            hasher.update(&buffer[..read]);
+            downloaded += read as u64;
+            on_progress(Progress { downloaded, total });
+        }
+    }
+    file.sync_all()?;
+    drop(file);
+
+    let digest = format!("{:x}", hasher.finalize());
+    if !digest.eq_ignore_ascii_case(request.sha256) {
+        fs::remove_file(&part).ok();
+        bail!(
+            "checksum mismatch for {}: expected {}, found {}",
+            request.dest.display(),
+            request.sha256,
+            digest
+        );
+    }
+
+    fs::rename(&part, request.dest)
+        .with_context(|| format!("failed to move {} into place", request.dest.display()))?;
+    Ok(digest)
+}
+
+/// GET `url`, asking for the bytes from `offset` on when resuming.
+fn fetch(client: &Client, url: &str, offset: u64) -> Result<reqwest::blocking::Response> {
+    let mut get = client.get(url);
+    if offset > 0 {
+        get = get.header(RANGE, format!("bytes={offset}-"));
+    }
+    get.send().with_context(|| format!("failed to contact {url}"))
+}
+
+fn hash_existing(file: &mut File, hasher: &mut Sha256) -> Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Start and total length (unless `*`) from a `Content-Range: bytes start-end/total` header.
+fn content_range(response: &reqwest::blocking::Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+fn ensure_free_space(dir: &Path, needed: u64) -> Result<()> {
+    let available = fs2::available_space(dir)
+        .map_err(|e| anyhow!("failed to query free space in {}: {e}", dir.display()))?;
+    if available < needed {
+        bail!(
+            "not enough disk space in {}: {} bytes needed, {} available",
+            dir.display(),
+            needed,
+            available
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tempfile::TempDir;
+    use tiny_http::{Header, Response, Server};
+
+    const PAYLOAD_LEN: usize = 300_000;
+
+    fn payload() -> Vec<u8> {
+        (0..PAYLOAD_LEN).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256_hex(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    /// How the fixture server answers a `Range` request.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Ranges {
+        Honoured,
+        Ignored,
+        /// Answers 206 with a slice starting elsewhere than requested.
+        Misaligned,
+    }
+
+    /// Serves `body` on a random local port, answering `Range` as `ranges` says.
+    /// Records the `Range` header of every request it receives.
+    fn fixture_server(body: Vec<u8>, ranges: Ranges) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/model.gguf", server.server_addr().to_ip().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let range = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Range"))
+                    .map(|h| h.value.to_string());
+                log.lock().unwrap().push(range.clone());
+
+                let start = range
+                    .filter(|_| ranges != Ranges::Ignored)
+                    .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok())
+                    .map(|start| if ranges == Ranges::Misaligned { start / 2 } else { start });
+                let response = match start {
+                    Some(start) if start >= body.len() => Response::from_data(Vec::new()).with_status_code(416),
+                    Some(start) => Response::from_data(body[start..].to_vec())
+                        .with_status_code(206)
+                        .with_header(
+                            Header::from_bytes(
+                                "Content-Range",
+                                format!("bytes {}-{}/{}", start, body.len() - 1, body.len()),
+                            )
+                            .unwrap(),
+                        ),
+                    None => Response::from_data(body.clone()),
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        (url, seen)
+    }
+
+    #[test]
+    fn downloads_verifies_and_reports_progress() {
+        let body = payload();
+        let (url, _) = fixture_server(body.clone(), Ranges::Honoured);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+        let sha = sha256_hex(&body);
+
+        let mut last = None;
+        let digest = download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &sha,
+                expected_size: Some(body.len() as u64),
+            },
+            |p| last = Some(p),
+        )
+        .unwrap();
+
+        assert_eq!(digest, sha);
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert!(!partial_path(&dest).exists());
+        assert_eq!(
+            last,
+            Some(Progress {
+                downloaded: body.len() as u64,
+                total: Some(body.len() as u64)
+            })
+        );
+    }
+
+    #[test]
+    fn resumes_from_partial_file_with_range_request() {
+        let body = payload();
+        let (url, seen) = fixture_server(body.clone(), Ranges::Honoured);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+        fs::write(partial_path(&dest), &body[..100_000]).unwrap();
+
+        let mut first = None;
+        download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &sha256_hex(&body),
+                expected_size: None,
+            },
+            |p| {
+                first.get_or_insert(p);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert_eq!(seen.lock().unwrap().as_slice(), [Some("bytes=100000-".to_string())]);
+        assert_eq!(first.unwrap().downloaded, 100_000);
+    }
+
+    #[test]
+    fn restarts_when_server_ignores_range() {
+        let body = payload();
+        let (url, _) = fixture_server(body.clone(), Ranges::Ignored);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+        fs::write(partial_path(&dest), b"stale bytes from another file").unwrap();
+
+        download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &sha256_hex(&body),
+                expected_size: None,
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn restarts_when_server_returns_a_different_range() {
+        let body = payload();
+        let (url, seen) = fixture_server(body.clone(), Ranges::Misaligned);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+        fs::write(partial_path(&dest), &body[..100_000]).unwrap();
+
+        download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &sha256_hex(&body),
+                expected_size: None,
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert_eq!(seen.lock().unwrap().as_slice(), [Some("bytes=100000-".to_string()), None]);
+    }
+
+    #[test]
+    fn completed_partial_file_is_verified_without_refetching() {
+        let body = payload();
+        let (url, _) = fixture_server(body.clone(), Ranges::Honoured);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+        fs::write(partial_path(&dest), &body).unwrap();
+
+        download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &sha256_hex(&body),
+                expected_size: None,
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn checksum_mismatch_discards_partial_and_keeps_dest_absent() {
+        let body = payload();
+        let (url, _) = fixture_server(body, Ranges::Honoured);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+
+        let err = download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &"0".repeat(64),
+                expected_size: None,
+            },
+            |_| {},
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("checksum mismatch"));
+        assert!(!dest.exists());
+        assert!(!partial_path(&dest).exists());
+    }
+
+    #[test]
+    fn insufficient_disk_space_fails_before_requesting() {
+        let (url, seen) = fixture_server(payload(), Ranges::Honoured);
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("model.gguf");
+
+        let err = download_verified(
+            &DownloadRequest {
+                url: &url,
+                dest: &dest,
+                sha256: &"0".repeat(64),
+                expected_size: Some(u64::MAX),
+            },
+            |_| {},
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("not enough disk space"));
+        assert!(seen.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+//! Install state of registry models inside a models directory.
+//!
+//! Hashing a multi-GB model on every listing is too slow, so verification results are
+//! kept in a small cache next to the models and reused while the file's length and
+//! modification time are unchanged.
+
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::model_registry::{compute_sha256, ModelRole, TrustedModel, TrustedRegistry};
+
+pub const VERIFICATION_CACHE_FILE: &str = ".verification.json";
+const UNTRUSTED_EXTENSION: &str = "gguf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelStatus {
+    /// Listed in the registry but not present on disk.
+    NotInstalled,
+    /// Present on disk, not hashed since it was last modified.
+    Unverified,
+    /// Present and its SHA-256 matches the registry.
+    Verified,
+    /// Present and its SHA-256 does not match the registry.
+    Corrupt,
+    /// A model file on disk that the registry does not list.
+    Untrusted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalModel {
+    /// Registry id, absent for untrusted files.
+    pub id: Option<String>,
+    pub name: String,
+    pub file: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub role: Option<ModelRole>,
+    pub status: ModelStatus,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedDigest {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    sha256: String,
+}
+
+impl CachedDigest {
+    fn fingerprint(metadata: &Metadata) -> Option<(u64, u64, u32)> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some((metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+    }
+
+    fn matches(&self, metadata: &Metadata) -> bool {
+        Self::fingerprint(metadata) == Some((self.len, self.modified_secs, self.modified_nanos))
+    }
+}
+
+/// Digests of model files keyed by file name, stored as
+/// `{"entries": {"<file>": {"len", "modified_secs", "modified_nanos", "sha256"}}}`.
+/// The gateway's `local_model` probe reads this file with its own declaration of the format;
+/// both crates test against `services/mcp-gateway/tests/fixtures/models/.verification.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VerificationCache {
+    entries: BTreeMap<String, CachedDigest>,
+}
+
+impl VerificationCache {
+    /// Load the cache from `models_dir`; a missing or unreadable cache is treated as empty.
+    pub fn load(models_dir: &Path) -> Self {
+        fs::read(models_dir.join(VERIFICATION_CACHE_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, models_dir: &Path) -> Result<()> {
+        let path = models_dir.join(VERIFICATION_CACHE_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Cached digest for `file`, if it was computed for the file as it is now.
+    fn digest(&self, file: &str, metadata: &Metadata) -> Option<&str> {
+        self.entries
+            .get(file)
+            .filter(|cached| cached.matches(metadata))
+            .map(|cached| cached.sha256.as_str())
+    }
+
+    pub fn record(&mut self, file: &str, metadata: &Metadata, sha256: String) {
+        match CachedDigest::fingerprint(metadata) {
+            Some((len, modified_secs, modified_nanos)) => {
+                self.entries.insert(
+                    file.to_string(),
+                    CachedDigest {
+                        len,
+                        modified_secs,
+                        modified_nanos,
+                        sha256,
+                    },
+                );
+            }
+            None => {
+                self.entries.remove(file);
+            }
+        }
+    }
+}
+
+/// Hash an installed registry model, record the result in the cache and return its status.
+pub fn verify_installed(model: &TrustedModel, models_dir: &Path) -> Result<ModelStatus> {
+    let path = models_dir.join(&model.file);
+    let Ok(metadata) = fs::metadata(&path) else {
+        return Ok(ModelStatus::NotInstalled);
+    };
+
+    let digest = compute_sha256(&path)?;
+    let status = status_for(model, &digest);
+    let mut cache = VerificationCache::load(models_dir);
+    cache.record(&model.file, &metadata, digest);
+    cache.save(models_dir)?;
+    Ok(status)
+}
+
+fn status_for(model: &TrustedModel, digest: &str) -> ModelStatus {
+    if digest.eq_ignore_ascii_case(&model.sha256) {
+        ModelStatus::Verified
+    } else {
+        ModelStatus::Corrupt
+    }
+}
+
+/// List every registry model plus any untrusted GGUF files found in `models_dir`.
+///
+/// Only cached digests are consulted; files that were never hashed (or changed since)
+/// are reported as [`ModelStatus::Unverified`].
+pub fn scan(registry: &TrustedRegistry, models_dir: &Path, active: Option<&Path>) -> Result<Vec<LocalModel>> {
+    let cache = VerificationCache::load(models_dir);
+    let active = active.map(canonical);
+    let is_active = |path: &Path| active.as_deref() == Some(canonical(path).as_path());
+
+    let mut models: Vec<LocalModel> = registry
+        .models
+        .iter()
+        .map(|model| {
+            let path = models_dir.join(&model.file);
+            let status = match fs::metadata(&path) {
+                Err(_) => ModelStatus::NotInstalled,
+                Ok(metadata) => match cache.digest(&model.file, &metadata) {
+                    Some(digest) => status_for(model, digest),
+                    None => ModelStatus::Unverified,
+                },
+            };
+            LocalModel {
+                id: Some(model.id.clone()),
+                name: model.name.clone(),
+                file: model.file.clone(),
+                active: status != ModelStatus::NotInstalled && is_active(&path),
+                path,
+                size_bytes: model.size_bytes,
+                role: Some(model.role),
+                status,
+            }
+        })
+        .collect();
+
+    let entries = match fs::read_dir(models_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(models),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", models_dir.display())),
+    };
+    let mut untrusted = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let is_gguf = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(UNTRUSTED_EXTENSION));
+        let file = entry.file_name().to_string_lossy().into_owned();
+        if !is_gguf || registry.find(&file).is_some() || !entry.file_type()?.is_file() {
+            continue;
+        }
+        untrusted.push(LocalModel {
+            id: None,
+            name: file.clone(),
+            file,
+            size_bytes: entry.metadata()?.len(),
+            role: None,
+            status: ModelStatus::Untrusted,
+            active: is_active(&path),
+            path,
+        });
+    }
+    untrusted.sort_by(|a, b| a.file.cmp(&b.file));
+    models.extend(untrusted);
+    Ok(models)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_registry::{PromptTemplate, Quantization, CURRENT_REGISTRY_VERSION};
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+
+    fn model(id: &str, contents: &[u8]) -> TrustedModel {
+        TrustedModel {
+            id: id.into(),
+            name: id.to_uppercase(),
+            file: format!("{id}.gguf"),
+            provider: "test".into(),
+            license: "MIT".into(),
+            uri: "http://127.0.0.1/unused".into(),
+            sha256: format!("{:x}", Sha256::digest(contents)),
+            size_bytes: contents.len() as u64,
+            context_length: 4096,
+            quantization: Quantization::Q4KM,
+            prompt_template: PromptTemplate::Mistral,
+            role: ModelRole::Instruct,
+            min_ram_bytes: 1,
+            trusted: true,
+        }
+    }
+
+    fn registry(models: Vec<TrustedModel>) -> TrustedRegistry {
+        TrustedRegistry {
+            registry_version: CURRENT_REGISTRY_VERSION,
+            issued_at: "2025-10-20T00:00:00Z".into(),
+            expires_at: "2026-10-20T00:00:00Z".into(),
+            models,
+        }
+    }
+
+    fn status_of(models: &[LocalModel], file: &str) -> (ModelStatus, bool) {
+        let m = models.iter().find(|m| m.file == file).unwrap();
+        (m.status, m.active)
+    }
+
+    #[test]
+    fn reports_install_verification_active_and_untrusted_state() {
+        let dir = TempDir::new().unwrap();
+        let good = model("good", b"good weights");
+        let bad = model("bad", b"expected weights");
+        let missing = model("missing", b"never downloaded");
+        fs::write(dir.path().join(&good.file), b"good weights").unwrap();
+        fs::write(dir.path().join(&bad.file), b"tampered weights").unwrap();
+        fs::write(dir.path().join("sideloaded.gguf"), b"who knows").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"ignored").unwrap();
+        let registry = registry(vec![good.clone(), bad.clone(), missing]);
+
+        let active = dir.path().join(&good.file);
+        let before = scan(&registry, dir.path(), Some(&active)).unwrap();
+        assert_eq!(status_of(&before, "good.gguf"), (ModelStatus::Unverified, true));
+        assert_eq!(status_of(&before, "bad.gguf"), (ModelStatus::Unverified, false));
+        assert_eq!(status_of(&before, "missing.gguf"), (ModelStatus::NotInstalled, false));
+        assert_eq!(status_of(&before, "sideloaded.gguf"), (ModelStatus::Untrusted, false));
+        assert_eq!(before.len(), 4);
+
+        assert_eq!(verify_installed(&good, dir.path()).unwrap(), ModelStatus::Verified);
+        assert_eq!(verify_installed(&bad, dir.path()).unwrap(), ModelStatus::Corrupt);
+
+        let after = scan(&registry, dir.path(), Some(&active)).unwrap();
+        assert_eq!(status_of(&after, "good.gguf"), (ModelStatus::Verified, true));
+        assert_eq!(status_of(&after, "bad.gguf"), (ModelStatus::Corrupt, false));
+    }
+
+    #[test]
+    fn cached_verification_is_dropped_when_file_changes() {
+        let dir = TempDir::new().unwrap();
+        let good = model("good", b"good weights");
+        let path = dir.path().join(&good.file);
+        fs::write(&path, b"good weights").unwrap();
+        verify_installed(&good, dir.path()).unwrap();
+
+        fs::write(&path, b"good weights, then appended").unwrap();
+        let models = scan(&registry(vec![good]), dir.path(), None).unwrap();
+        assert_eq!(status_of(&models, "good.gguf"), (ModelStatus::Unverified, false));
+    }
+
+    #[test]
+    fn missing_models_dir_lists_registry_only() {
+        let dir = TempDir::new().unwrap();
+        let models = scan(&registry(vec![model("a", b"a")]), &dir.path().join("absent"), None).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].status, ModelStatus::NotInstalled);
+    }
+
+    #[test]
+    fn cache_format_matches_the_gateway_fixture() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../services/mcp-gateway/tests/fixtures/models");
+        let fixture: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join(VERIFICATION_CACHE_FILE)).unwrap()).unwrap();
+        let cache = VerificationCache::load(&dir);
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(serde_json::to_value(&cache).unwrap(), fixture);
+    }
+}
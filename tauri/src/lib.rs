@@ -1 +1,4 @@
+pub mod download;
+pub mod inventory;
 pub mod model_registry;
+pub mod registry_schema;
@@ -1,18 +1,31 @@
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::Read;
 
 use anyhow::{anyhow, Context, Result};
-use pgp::{composed::SignedPublicKey, SignedMessage, Deserializable};
+use pgp::{composed::SignedPublicKey, Deserializable, StandaloneSignature};
+use sha2::{Digest, Sha256};
+
+pub use crate::registry_schema::{
+    ModelRole, PromptTemplate, Quantization, TrustedModel, TrustedRegistry, CURRENT_REGISTRY_VERSION,
+};
 
 const REGISTRY_DIR: &str = "../services/mcp-gateway/models";
 const REGISTRY_FILE: &str = "trusted_models.json";
 const SIGNATURE_FILE: &str = "trusted_models.json.asc";
 const PUBLIC_KEY_FILE: &str = "publickey.asc";
 
-fn default_registry_dir() -> PathBuf {
+pub fn default_registry_dir() -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR")).join(REGISTRY_DIR)
 }
 
+/// Registry location, overridable with `COUNSEL_REGISTRY_DIR` for packaged builds.
+pub fn registry_dir() -> PathBuf {
+    std::env::var_os("COUNSEL_REGISTRY_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_registry_dir)
+}
+
 fn read_file(path: &Path) -> Result<Vec<u8>> {
     fs::read(path).with_context(|| format!("failed to read {}", path.display()))
 }
@@ -23,12 +36,21 @@ fn parse_public_key(bytes: &[u8]) -> Result<SignedPublicKey> {
     Ok(key)
 }
 
-fn parse_signature(bytes: &[u8]) -> Result<SignedMessage> {
-    let (sig, _rest) = SignedMessage::from_armor_single(bytes)
+fn parse_signature(bytes: &[u8]) -> Result<StandaloneSignature> {
+    let (sig, _rest) = StandaloneSignature::from_armor_single(bytes)
         .map_err(|_| anyhow!("invalid signature file"))?;
     Ok(sig)
 }
 
+fn verify_bytes(public_key_bytes: &[u8], registry_bytes: &[u8], signature_bytes: &[u8]) -> Result<()> {
+    let public_key = parse_public_key(public_key_bytes)?;
+    let signature = parse_signature(signature_bytes)?;
+
+    signature
+        .verify(&public_key, registry_bytes)
+        .map_err(|_| anyhow!("signature verification failed"))
+}
+
 pub fn verify_registry_signature_with_paths<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
     public_key_path: P,
     registry_path: Q,
@@ -38,14 +60,7 @@ pub fn verify_registry_signature_with_paths<P: AsRef<Path>, Q: AsRef<Path>, R: A
     let registry_bytes = read_file(registry_path.as_ref())?;
     let signature_bytes = read_file(signature_path.as_ref())?;
 
-    let public_key = parse_public_key(&public_key_bytes)?;
-    let signature = parse_signature(&signature_bytes)?;
-
-    signature
-        .verify(&public_key, &registry_bytes)
-        .map_err(|_| anyhow!("signature verification failed"))?;
-
-    Ok(())
+    verify_bytes(&public_key_bytes, &registry_bytes, &signature_bytes)
 }
 
 pub fn verify_registry_signature() -> Result<()> {
@@ -57,6 +72,35 @@ pub fn verify_registry_signature() -> Result<()> {
     )
 }
 
+/// Verify the registry signature in `dir` and parse the exact bytes that were verified.
+pub fn load_verified_registry<P: AsRef<Path>>(dir: P) -> Result<TrustedRegistry> {
+    let dir = dir.as_ref();
+    let public_key_bytes = read_file(&dir.join(PUBLIC_KEY_FILE))?;
+    let registry_bytes = read_file(&dir.join(REGISTRY_FILE))?;
+    let signature_bytes = read_file(&dir.join(SIGNATURE_FILE))?;
+
+    verify_bytes(&public_key_bytes, &registry_bytes, &signature_bytes)?;
+    TrustedRegistry::from_slice(&registry_bytes)
+        .with_context(|| format!("malformed {}", dir.join(REGISTRY_FILE).display()))
+}
+
+/// Stream a file through SHA-256 and return the lowercase hex digest.
+pub fn compute_sha256<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 pub fn verify_or_repair_model_from_registry() -> Result<()> {
     verify_registry_signature()?;
     // existing logic would follow...
@@ -69,6 +113,12 @@ mod tests {
     use tempfile::NamedTempFile;
     use std::io::Write;
 
+    /// A copy of the registry signed by a throwaway test key, for the positive path of
+    /// `load_verified_registry` alone; everything else runs against the shipped registry.
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/registry")
+    }
+
     fn fixture_paths() -> (PathBuf, PathBuf, PathBuf) {
         let base = default_registry_dir();
         (
@@ -84,6 +134,30 @@ mod tests {
         verify_registry_signature_with_paths(pubkey, registry, signature).unwrap();
     }
 
+    #[test]
+    fn verified_registry_loads_from_a_signed_directory() {
+        let registry = load_verified_registry(fixture_dir()).unwrap();
+        assert_eq!(registry.registry_version, CURRENT_REGISTRY_VERSION);
+
+        // The release key did not sign the fixture.
+        let dir = tempfile::tempdir().unwrap();
+        for file in [REGISTRY_FILE, SIGNATURE_FILE] {
+            fs::copy(fixture_dir().join(file), dir.path().join(file)).unwrap();
+        }
+        fs::copy(default_registry_dir().join(PUBLIC_KEY_FILE), dir.path().join(PUBLIC_KEY_FILE)).unwrap();
+        assert!(load_verified_registry(dir.path()).is_err());
+    }
+
+    /// Fails whenever `trusted_models.json` changes without a new release signature. The file
+    /// ships in the v1 form it was signed in and is migrated on load.
+    #[test]
+    fn shipped_registry_is_signed_by_the_release_key() {
+        match load_verified_registry(default_registry_dir()) {
+            Ok(registry) => assert_eq!(registry.registry_version, CURRENT_REGISTRY_VERSION),
+            Err(e) => panic!("shipped registry does not verify ({e:#}); re-sign it with `scripts/key-manager.sh sign`"),
+        }
+    }
+
     #[test]
     fn tampering_breaks_signature() {
         let (pubkey, registry, signature) = fixture_paths();
@@ -100,4 +174,26 @@ mod tests {
         let result = verify_registry_signature_with_paths(pubkey, &temp_path, signature);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn registry_fixture_parses_and_finds_by_id_or_file() {
+        let (_, registry, _) = fixture_paths();
+        let parsed = TrustedRegistry::from_slice(&read_file(&registry).unwrap()).unwrap();
+        assert_eq!(parsed.registry_version, CURRENT_REGISTRY_VERSION);
+
+        let model = parsed.find("counsel-llm-italian-v1-instruct").unwrap();
+        assert_eq!(model.file, "counsel-llm-italian-v1-instruct.bin");
+        assert_eq!(parsed.find("mistral-7b-instruct.Q4_K_M.gguf").unwrap().id, "mistral-7b-instruct-q4_k_m");
+        assert!(parsed.find("counsel-llm-italian").is_none());
+    }
+
+    #[test]
+    fn sha256_matches_known_digest() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(b"abc").unwrap();
+        assert_eq!(
+            compute_sha256(temp.path()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
 }
@@ -0,0 +1,354 @@
+//! Typed schema for `trusted_models.json`.
+//!
+//! The current format is version 2. Version 1 registries (free-form `size` strings and
+//! no runtime metadata) are still accepted and migrated on load.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const CURRENT_REGISTRY_VERSION: u32 = 2;
+
+const GIB: u64 = 1 << 30;
+/// Context length assumed for v1 entries whose file name does not state one.
+const DEFAULT_CONTEXT_LENGTH: u32 = 4096;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedRegistry {
+    pub registry_version: u32,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub models: Vec<TrustedModel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedModel {
+    pub id: String,
+    pub name: String,
+    pub file: String,
+    pub provider: String,
+    pub license: String,
+    pub uri: String,
+    pub sha256: String,
+    /// Download size in bytes.
+    pub size_bytes: u64,
+    /// Maximum context window in tokens.
+    pub context_length: u32,
+    pub quantization: Quantization,
+    pub prompt_template: PromptTemplate,
+    pub role: ModelRole,
+    /// Minimum system memory needed to load the model.
+    pub min_ram_bytes: u64,
+    pub trusted: bool,
+}
+
+/// Weight quantization of a GGUF/GGML model file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantization {
+    #[serde(rename = "F32")]
+    F32,
+    #[serde(rename = "F16")]
+    F16,
+    #[serde(rename = "Q8_0")]
+    Q8_0,
+    #[serde(rename = "Q6_K")]
+    Q6K,
+    #[serde(rename = "Q5_K_M")]
+    Q5KM,
+    #[serde(rename = "Q5_K_S")]
+    Q5KS,
+    #[serde(rename = "Q5_0")]
+    Q5_0,
+    #[serde(rename = "Q4_K_M")]
+    Q4KM,
+    #[serde(rename = "Q4_K_S")]
+    Q4KS,
+    #[serde(rename = "Q4_0")]
+    Q4_0,
+    #[serde(rename = "Q3_K_M")]
+    Q3KM,
+    #[serde(rename = "Q2_K")]
+    Q2K,
+    /// Not stated by the publisher.
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl Quantization {
+    const ALL: [Quantization; 12] = [
+        Quantization::F32,
+        Quantization::F16,
+        Quantization::Q8_0,
+        Quantization::Q6K,
+        Quantization::Q5KM,
+        Quantization::Q5KS,
+        Quantization::Q5_0,
+        Quantization::Q4KM,
+        Quantization::Q4KS,
+        Quantization::Q4_0,
+        Quantization::Q3KM,
+        Quantization::Q2K,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Quantization::F32 => "F32",
+            Quantization::F16 => "F16",
+            Quantization::Q8_0 => "Q8_0",
+            Quantization::Q6K => "Q6_K",
+            Quantization::Q5KM => "Q5_K_M",
+            Quantization::Q5KS => "Q5_K_S",
+            Quantization::Q5_0 => "Q5_0",
+            Quantization::Q4KM => "Q4_K_M",
+            Quantization::Q4KS => "Q4_K_S",
+            Quantization::Q4_0 => "Q4_0",
+            Quantization::Q3KM => "Q3_K_M",
+            Quantization::Q2K => "Q2_K",
+            Quantization::Unknown => "unknown",
+        }
+    }
+
+    /// Recognise the quantization suffix in a file name such as `model.Q4_K_M.gguf`.
+    fn from_file_name(file: &str) -> Self {
+        let upper = file.to_ascii_uppercase();
+        Self::ALL
+            .into_iter()
+            .find(|q| {
+                upper
+                    .split(['.', '-'])
+                    .any(|part| part == q.as_str())
+            })
+            .unwrap_or(Quantization::Unknown)
+    }
+}
+
+/// Chat template family the runtime must use to format prompts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptTemplate {
+    /// `[INST] ... [/INST]`
+    Mistral,
+    /// `<|user|> ... <|end|><|assistant|>`
+    Phi3,
+    /// `<|im_start|>role ... <|im_end|>`
+    Chatml,
+    /// Plain text, no chat markup.
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRole {
+    Instruct,
+    Embedding,
+}
+
+impl TrustedRegistry {
+    /// Parse registry JSON of any supported version into the current schema.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct VersionProbe {
+            registry_version: u32,
+        }
+
+        let probe: VersionProbe =
+            serde_json::from_slice(bytes).context("registry is missing registry_version")?;
+        match probe.registry_version {
+            1 => {
+                let v1: v1::Registry = serde_json::from_slice(bytes).context("malformed v1 registry")?;
+                v1.migrate()
+            }
+            CURRENT_REGISTRY_VERSION => serde_json::from_slice(bytes).context("malformed v2 registry"),
+            other => bail!("unsupported registry_version {other}"),
+        }
+    }
+
+    /// Look up a model by exact registry id or exact file name.
+    pub fn find(&self, key: &str) -> Option<&TrustedModel> {
+        self.models.iter().find(|m| m.id == key || m.file == key)
+    }
+}
+
+/// The original registry format, kept so older signed registries still load.
+mod v1 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub(super) struct Registry {
+        issued_at: String,
+        expires_at: String,
+        models: Vec<Model>,
+    }
+
+    #[derive(Deserialize)]
+    struct Model {
+        id: String,
+        name: String,
+        file: String,
+        provider: String,
+        license: String,
+        uri: String,
+        sha256: String,
+        size: String,
+        trusted: bool,
+    }
+
+    impl Registry {
+        pub(super) fn migrate(self) -> Result<TrustedRegistry> {
+            let models = self
+                .models
+                .into_iter()
+                .map(Model::migrate)
+                .collect::<Result<_>>()?;
+            Ok(TrustedRegistry {
+                registry_version: CURRENT_REGISTRY_VERSION,
+                issued_at: self.issued_at,
+                expires_at: self.expires_at,
+                models,
+            })
+        }
+    }
+
+    impl Model {
+        fn migrate(self) -> Result<TrustedModel> {
+            let size_bytes = parse_size(&self.size)
+                .with_context(|| format!("model {} has unparseable size {:?}", self.id, self.size))?;
+            let id = self.id.to_ascii_lowercase();
+            let role = if id.contains("embed") {
+                ModelRole::Embedding
+            } else {
+                ModelRole::Instruct
+            };
+            let prompt_template = if role == ModelRole::Embedding {
+                PromptTemplate::Raw
+            } else if id.starts_with("mistral") {
+                PromptTemplate::Mistral
+            } else if id.starts_with("phi-3") {
+                PromptTemplate::Phi3
+            } else {
+                PromptTemplate::Raw
+            };
+
+            Ok(TrustedModel {
+                context_length: context_from_file_name(&self.file).unwrap_or(DEFAULT_CONTEXT_LENGTH),
+                quantization: Quantization::from_file_name(&self.file),
+                prompt_template,
+                role,
+                // Weights plus KV cache and runtime overhead.
+                min_ram_bytes: size_bytes + GIB,
+                size_bytes,
+                id: self.id,
+                name: self.name,
+                file: self.file,
+                provider: self.provider,
+                license: self.license,
+                uri: self.uri,
+                sha256: self.sha256,
+                trusted: self.trusted,
+            })
+        }
+    }
+
+    /// Parse "4.2 GB" into bytes using binary multiples, so disk and memory estimates
+    /// err on the large side.
+    pub(super) fn parse_size(size: &str) -> Option<u64> {
+        let (value, unit) = size.trim().split_once(' ')?;
+        let value: f64 = value.parse().ok()?;
+        let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+            "B" => 1,
+            "KB" | "KIB" => 1 << 10,
+            "MB" | "MIB" => 1 << 20,
+            "GB" | "GIB" => 1 << 30,
+            "TB" | "TIB" => 1 << 40,
+            _ => return None,
+        };
+        (value >= 0.0).then(|| (value * multiplier as f64).ceil() as u64)
+    }
+
+    /// Context size encoded in names such as `phi-3-mini-4k-instruct`.
+    fn context_from_file_name(file: &str) -> Option<u32> {
+        file.to_ascii_lowercase()
+            .split(['.', '-', '_'])
+            .find_map(|part| part.strip_suffix('k')?.parse::<u32>().ok())
+            .map(|k| k * 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_REGISTRY: &str = r#"{
+        "registry_version": 1,
+        "issued_at": "2025-10-20T00:00:00Z",
+        "expires_at": "2026-10-20T00:00:00Z",
+        "models": [
+            {
+                "id": "phi-3-mini-instruct-q4_k_m",
+                "name": "Phi-3 Mini Instruct (Q4_K_M)",
+                "file": "phi-3-mini-4k-instruct.Q4_K_M.gguf",
+                "provider": "huggingface/TheBloke",
+                "license": "MIT",
+                "uri": "https://example.com/phi.gguf",
+                "sha256": "a21ad4c26f53211e39df6b374f640093226f55da16d3f7a7c10c3a90ab5c04b2",
+                "size": "2.7 GB",
+                "trusted": true
+            },
+            {
+                "id": "counsel-llm-italian-v1",
+                "name": "Counsel LLM Italian Base v1",
+                "file": "counsel-llm-italian-v1.bin",
+                "provider": "counsel-ai",
+                "license": "proprietary",
+                "uri": "https://example.com/models/counsel-llm-italian-v1.bin",
+                "sha256": "7a3c3ff4c5b76b76979e3c927ab0fb70d137e75ebf70d0da0b175d5b4e6a3f0b",
+                "size": "5.0 GB",
+                "trusted": true
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn v1_registry_migrates_to_current_schema() {
+        let registry = TrustedRegistry::from_slice(V1_REGISTRY.as_bytes()).unwrap();
+        assert_eq!(registry.registry_version, CURRENT_REGISTRY_VERSION);
+
+        let phi = registry.find("phi-3-mini-instruct-q4_k_m").unwrap();
+        assert_eq!(phi.size_bytes, (2.7 * GIB as f64).ceil() as u64);
+        assert_eq!(phi.context_length, 4096);
+        assert_eq!(phi.quantization, Quantization::Q4KM);
+        assert_eq!(phi.prompt_template, PromptTemplate::Phi3);
+        assert_eq!(phi.role, ModelRole::Instruct);
+        assert_eq!(phi.min_ram_bytes, phi.size_bytes + GIB);
+
+        let italian = registry.find("counsel-llm-italian-v1").unwrap();
+        assert_eq!(italian.size_bytes, 5 * GIB);
+        assert_eq!(italian.quantization, Quantization::Unknown);
+        assert_eq!(italian.prompt_template, PromptTemplate::Raw);
+    }
+
+    #[test]
+    fn migrated_registry_round_trips_as_v2() {
+        let registry = TrustedRegistry::from_slice(V1_REGISTRY.as_bytes()).unwrap();
+        let json = serde_json::to_vec(&registry).unwrap();
+        assert_eq!(TrustedRegistry::from_slice(&json).unwrap(), registry);
+    }
+
+    #[test]
+    fn find_matches_exact_id_or_file_only() {
+        let registry = TrustedRegistry::from_slice(V1_REGISTRY.as_bytes()).unwrap();
+        assert!(registry.find("phi-3-mini-4k-instruct.Q4_K_M.gguf").is_some());
+        assert!(registry.find("counsel-llm-italian-v1.bin").is_some());
+        assert!(registry.find("Q4_K_M.gguf").is_none());
+        assert!(registry.find("counsel-llm-italian").is_none());
+    }
+
+    #[test]
+    fn unsupported_versions_and_bad_sizes_are_rejected() {
+        let future = V1_REGISTRY.replace("\"registry_version\": 1", "\"registry_version\": 9");
+        assert!(TrustedRegistry::from_slice(future.as_bytes()).is_err());
+
+        let bad_size = V1_REGISTRY.replace("\"2.7 GB\"", "\"large\"");
+        assert!(TrustedRegistry::from_slice(bad_size.as_bytes()).is_err());
+        assert_eq!(v1::parse_size("512 MB"), Some(512 << 20));
+    }
+}